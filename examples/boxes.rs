@@ -0,0 +1,374 @@
+// The bouncing boxes demo. Run it with `cargo run --release --example boxes`.
+
+extern crate rand;
+#[macro_use]
+extern crate violetta;
+
+use rand::Rng;
+use std::env;
+use std::process;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use violetta::components::renderable::Renderable;
+use violetta::context::generational::Key;
+use violetta::context::Context;
+use violetta::entities;
+use violetta::error::EngineError;
+use violetta::game;
+
+// Frames kept for the timing summary, about half an hour at 60 frames per second
+const MAX_SUMMARY_FRAMES: u64 = 100_000;
+
+const USAGE: &'static str = "Usage: boxes [options]
+
+Options:
+    --entities N          Number of boxes (default 1700)
+    --seed N              Seed of the random number generator
+    --size WIDTHxHEIGHT   Window size in logical pixels
+    --vsync on|off        Turning vsync off also stops frame pacing (default on)
+    --frames N            Exit after N frames and print a timing summary
+    --headless            Run without a window
+    --record PATH         Record a replay
+    --replay PATH         Play a replay
+    --verify-replay PATH  Play a replay, checking the world against it every frame
+    --help                Print this message";
+
+const DEFAULT_ENTITY_COUNT: usize = 1700;
+
+struct Options {
+    entity_count: usize,
+    seed: Option<u64>,
+    window_size: Option<(u32, u32)>,
+    vsync: bool,
+    frames: Option<u64>,
+    headless: bool,
+    record: Option<String>,
+    replay: Option<(String, bool)>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            entity_count: DEFAULT_ENTITY_COUNT,
+            seed: None,
+            window_size: None,
+            vsync: true,
+            frames: None,
+            headless: false,
+            record: None,
+            replay: None,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                return args
+                    .next()
+                    .map(|value| value.clone())
+                    .ok_or_else(|| format!("{} needs a value", arg));
+            };
+
+            match arg.as_str() {
+                "--entities" => options.entity_count = parse_number(arg, &value()?)?,
+                "--seed" => options.seed = Some(parse_number(arg, &value()?)?),
+                "--size" => {
+                    let size = value()?;
+                    let mut parts = size.splitn(2, 'x');
+                    let width = parse_number(arg, parts.next().unwrap_or(""))?;
+                    let height = parse_number(arg, parts.next().unwrap_or(""))?;
+                    options.window_size = Some((width, height));
+                }
+                "--vsync" => {
+                    options.vsync = match value()?.as_str() {
+                        "on" => true,
+                        "off" => false,
+                        _ => return Err("--vsync needs on or off".to_string()),
+                    }
+                }
+                "--frames" => options.frames = Some(parse_number(arg, &value()?)?),
+                "--headless" => options.headless = true,
+                "--record" => options.record = Some(value()?),
+                "--replay" => options.replay = Some((value()?, false)),
+                "--verify-replay" => options.replay = Some((value()?, true)),
+                _ => return Err(format!("Unknown option {}", arg)),
+            }
+        }
+
+        return Ok(options);
+    }
+}
+
+fn parse_number<T: FromStr>(option: &str, value: &str) -> Result<T, String> {
+    return value
+        .parse()
+        .map_err(|_| format!("{} needs a number, got {}", option, value));
+}
+
+struct Game {
+    entity_count: usize,
+    frame_limit: Option<u64>,
+    frames: u64,
+    started: Option<Instant>,
+    objs: Vec<Box<GameBox>>,
+}
+
+impl Game {
+    pub fn new(entity_count: usize, frame_limit: Option<u64>) -> Game {
+        return Game {
+            entity_count: entity_count,
+            frame_limit: frame_limit,
+            frames: 0,
+            started: None,
+            objs: Vec::new(),
+        };
+    }
+
+    fn print_summary(&self, context: &Context) {
+        let elapsed = self
+            .started
+            .map(|started| started.elapsed())
+            .unwrap_or(Duration::from_millis(0));
+        let seconds = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9;
+
+        println!(
+            "{} frames of {} boxes in {:.3}s, {:.1} fps",
+            self.frames,
+            self.entity_count,
+            seconds,
+            self.frames as f64 / seconds.max(1e-9)
+        );
+        println!(
+            "{:<16} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            "stage", "avg ms", "p50 ms", "p95 ms", "p99 ms", "max ms"
+        );
+        for stage in context.profiler.stages() {
+            if let Some(stats) = context.profiler.stats(stage) {
+                println!(
+                    "{:<16} {:>10.3} {:>10.3} {:>10.3} {:>10.3} {:>10.3}",
+                    stage,
+                    milliseconds(stats.avg),
+                    milliseconds(stats.p50),
+                    milliseconds(stats.p95),
+                    milliseconds(stats.p99),
+                    milliseconds(stats.max)
+                );
+            }
+        }
+    }
+}
+
+fn milliseconds(duration: Duration) -> f64 {
+    return duration.as_secs() as f64 * 1e3 + duration.subsec_nanos() as f64 * 1e-6;
+}
+
+impl game::Game for Game {
+    fn initialize(&mut self, context: &mut Context) -> Result<(), EngineError> {
+        vlog_info!("Initializing game");
+
+        for _ in 0..self.entity_count {
+            self.objs.push(Box::new(GameBox::new(context)));
+        }
+
+        // Keep every frame of a benchmark run for the summary, or the last ones of long runs
+        if let Some(frame_limit) = self.frame_limit {
+            let history_length = frame_limit.min(MAX_SUMMARY_FRAMES);
+            context.profiler.set_history_length(history_length as usize);
+        }
+        self.started = Some(Instant::now());
+
+        return Ok(());
+    }
+
+    fn game_loop(&mut self, context: &mut Context) {
+        for obj in &mut self.objs {
+            obj.update(context);
+        }
+
+        self.frames += 1;
+        if let Some(frame_limit) = self.frame_limit {
+            if self.frames >= frame_limit {
+                context.request_exit();
+            }
+        }
+    }
+
+    fn on_exit(&mut self, context: &mut Context) {
+        if self.frame_limit.is_some() {
+            self.print_summary(context);
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            vlog_error!("{}", message);
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    let game = Game::new(options.entity_count, options.frames);
+    let mut game_runner = game::GameRunner::new(Box::new(game));
+
+    // Passing the seed of an earlier run reproduces it
+    if let Some(seed) = options.seed {
+        game_runner.set_seed(seed);
+    }
+    if let Some((width, height)) = options.window_size {
+        game_runner.set_window_size(width, height);
+    }
+    if !options.vsync {
+        game_runner.set_vsync(false);
+        game_runner.set_frame_period(None);
+    }
+    game_runner.set_headless(options.headless);
+
+    // Replays of bug reports are recorded with --record and checked with --verify-replay
+    if let Some(path) = options.record {
+        game_runner.record_replay(path);
+    }
+    if let Some((path, verify)) = options.replay {
+        game_runner.play_replay(path, verify);
+    }
+
+    game_runner.add_default_plugins();
+
+    if let Err(err) = game_runner.start() {
+        vlog_error!("{}", err);
+        process::exit(1);
+    }
+
+    if let Some(frame) = game_runner.replay_divergence() {
+        vlog_error!("Replay diverged at frame {}", frame);
+        process::exit(2);
+    }
+}
+
+struct GameBox {
+    obj: GameObj,
+    direction: (f32, f32),
+}
+
+impl GameBox {
+    fn new(context: &mut Context) -> GameBox {
+        let width = context.screen.width as f32;
+        let height = context.screen.height as f32;
+        let (x, y, z, dx, dy, size, speed) = {
+            let random = context.random.stream("gameplay");
+            (
+                random.gen::<f32>() * width,
+                random.gen::<f32>() * height,
+                2.0 * random.gen::<f32>() - 1.0,
+                2.0 * random.gen::<f32>() - 1.0,
+                2.0 * random.gen::<f32>() - 1.0,
+                5.0 + 100.0 * random.gen::<f32>(),
+                -25.0 + 50.0 * random.gen::<f32>(),
+            )
+        };
+        let mut game_obj = GameObj::new(context);
+        game_obj.set_position(context, x, y);
+        game_obj.set_size(context, size, size);
+        game_obj.set_z_index(context, z);
+        let game_box = GameBox {
+            obj: game_obj,
+            direction: (speed * dx, speed * dy),
+        };
+
+        return game_box;
+    }
+
+    fn update(&mut self, context: &mut Context) {
+        let pos = self.obj.get_position(context);
+        let mut translation_x = self.direction.0;
+        let mut translation_y = self.direction.1;
+
+        let width = context.screen.width as f32;
+        let height = context.screen.height as f32;
+
+        if pos.0 < 0.0 {
+            translation_x = width;
+        }
+
+        if pos.0 > width {
+            translation_x = -width;
+        }
+
+        if pos.1 < 0.0 {
+            translation_y = height;
+        }
+
+        if pos.1 > height {
+            translation_y = -height;
+        }
+
+        self.obj
+            .set_position(context, pos.0 + translation_x, pos.1 + translation_y);
+    }
+}
+
+struct GameObj {
+    key: Key,
+}
+
+impl GameObj {
+    fn new(context: &mut Context) -> GameObj {
+        let mut entity = entities::Entity::new(32);
+        let renderable = Renderable::new();
+        entity.set_component::<Renderable>(renderable);
+        let key = context.entities.add(Box::new(entity));
+        return GameObj { key };
+    }
+
+    fn set_size(&mut self, context: &mut Context, width: f32, height: f32) {
+        if let Some(renderable) = context
+            .entities
+            .get(&self.key)
+            .and_then({ |entity| entity.get_component::<Renderable>(Renderable::name()) })
+        {
+            renderable.set_size(width, height);
+        }
+    }
+
+    fn get_position(&mut self, context: &mut Context) -> (f32, f32) {
+        if let Some(renderable) = context
+            .entities
+            .get(&self.key)
+            .and_then({ |entity| entity.get_component::<Renderable>(Renderable::name()) })
+        {
+            return (renderable.x, renderable.y);
+        }
+
+        return (0.0, 0.0);
+    }
+
+    fn set_position(&mut self, context: &mut Context, x: f32, y: f32) {
+        if let Some(renderable) = context
+            .entities
+            .get(&self.key)
+            .and_then({ |entity| entity.get_component::<Renderable>(Renderable::name()) })
+        {
+            let z = renderable.z;
+            renderable.set_position(x, y, z);
+        }
+    }
+
+    fn set_z_index(&mut self, context: &mut Context, z: f32) {
+        if let Some(renderable) = context
+            .entities
+            .get(&self.key)
+            .and_then({ |entity| entity.get_component::<Renderable>(Renderable::name()) })
+        {
+            let x = renderable.x;
+            let y = renderable.y;
+            renderable.set_position(x, y, z);
+        }
+    }
+}
@@ -0,0 +1,407 @@
+use components::Component;
+use context::clock::TimeDomain;
+use context::generational::Key;
+use std::time::Duration;
+
+/// A clip an animator is playing and how far into it it is
+#[derive(Debug, Clone)]
+pub struct Playback {
+    pub clip: String,
+    /// Seconds into the clip
+    pub time: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Fade {
+    elapsed: Duration,
+    duration: Duration,
+}
+
+/// Plays clips from `context.animations` on its entity and the children bound to it. Two
+/// clips can be blended, either with a fixed weight or by cross-fading from one to the
+/// other.
+#[derive(Debug)]
+pub struct Animator {
+    pub speed: f32,
+    /// Whether clips start over when they end, otherwise they hold their last frame
+    pub looping: bool,
+
+    domain: TimeDomain,
+    current: Option<Playback>,
+    blended: Option<Playback>,
+    // How much of the blended clip is mixed in, from 0 to 1
+    weight: f32,
+    fade: Option<Fade>,
+    // What the fields that only the blended clip animates were when the blend started, by
+    // path and field, so the mix doesn't build on values it wrote itself
+    bases: Vec<(String, String, f32)>,
+    children: Vec<(String, Key)>,
+}
+
+impl Component for Animator {
+    fn name(&self) -> &'static str {
+        return Animator::name();
+    }
+
+    fn save_state(&self, out: &mut Vec<u8>) {
+        for playback in &[&self.current, &self.blended] {
+            match **playback {
+                Some(ref playback) => {
+                    out.push(1);
+                    write_string(out, &playback.clip);
+                    out.extend_from_slice(&playback.time.to_bits().to_le_bytes());
+                }
+                None => out.push(0),
+            }
+        }
+        out.extend_from_slice(&self.weight.to_bits().to_le_bytes());
+
+        match self.fade {
+            Some(fade) => {
+                out.push(1);
+                out.extend_from_slice(&nanos_of(fade.elapsed).to_le_bytes());
+                out.extend_from_slice(&nanos_of(fade.duration).to_le_bytes());
+            }
+            None => out.push(0),
+        }
+
+        out.push(self.looping as u8);
+        out.extend_from_slice(&self.speed.to_bits().to_le_bytes());
+        out.push(match self.domain {
+            TimeDomain::Real => 0,
+            TimeDomain::Ui => 1,
+            TimeDomain::Game => 2,
+        });
+
+        out.extend_from_slice(&(self.bases.len() as u32).to_le_bytes());
+        for &(ref path, ref field, value) in &self.bases {
+            write_string(out, path);
+            write_string(out, field);
+            out.extend_from_slice(&value.to_bits().to_le_bytes());
+        }
+    }
+
+    fn load_state(&mut self, data: &[u8]) {
+        let mut reader = StateReader { data: data };
+        let current = match reader.playback() {
+            Some(current) => current,
+            None => return,
+        };
+        let blended = match reader.playback() {
+            Some(blended) => blended,
+            None => return,
+        };
+        let weight = match reader.f32() {
+            Some(weight) => weight,
+            None => return,
+        };
+        let fade = match reader.u8() {
+            Some(0) => None,
+            Some(_) => match (reader.u64(), reader.u64()) {
+                (Some(elapsed), Some(duration)) => Some(Fade {
+                    elapsed: duration_of(elapsed),
+                    duration: duration_of(duration),
+                }),
+                _ => return,
+            },
+            None => return,
+        };
+        let (looping, speed) = match (reader.u8(), reader.f32()) {
+            (Some(looping), Some(speed)) => (looping != 0, speed),
+            _ => return,
+        };
+        let domain = match reader.u8() {
+            Some(0) => TimeDomain::Real,
+            Some(1) => TimeDomain::Ui,
+            Some(_) => TimeDomain::Game,
+            None => return,
+        };
+        let count = match reader.u32() {
+            Some(count) => count,
+            None => return,
+        };
+        let mut bases = Vec::new();
+        for _ in 0..count {
+            match (reader.string(), reader.string(), reader.f32()) {
+                (Some(path), Some(field), Some(value)) => bases.push((path, field, value)),
+                _ => return,
+            }
+        }
+
+        self.current = current;
+        self.blended = blended;
+        self.weight = weight;
+        self.fade = fade;
+        self.looping = looping;
+        self.speed = speed;
+        self.domain = domain;
+        self.bases = bases;
+    }
+}
+
+impl Animator {
+    pub fn name() -> &'static str {
+        return "animator";
+    }
+
+    pub fn new() -> Box<Animator> {
+        let animator = Animator {
+            speed: 1.0,
+            looping: true,
+
+            domain: TimeDomain::Game,
+            current: None,
+            blended: None,
+            weight: 0.0,
+            fade: None,
+            bases: Vec::new(),
+            children: Vec::new(),
+        };
+        return Box::new(animator);
+    }
+
+    /// Which clock the clips follow, game time by default
+    pub fn set_domain(&mut self, domain: TimeDomain) {
+        self.domain = domain;
+    }
+
+    pub fn domain(&self) -> TimeDomain {
+        return self.domain;
+    }
+
+    /// Lets tracks with this path animate the child
+    pub fn bind_child(&mut self, path: &str, child: Key) {
+        self.children.retain(|&(ref existing, _)| existing != path);
+        self.children.push((path.to_string(), child));
+    }
+
+    pub fn children(&self) -> &[(String, Key)] {
+        return &self.children;
+    }
+
+    /// Plays the clip from the start, stopping any blend
+    pub fn play(&mut self, clip: &str) {
+        self.current = Some(Playback {
+            clip: clip.to_string(),
+            time: 0.0,
+        });
+        self.blended = None;
+        self.weight = 0.0;
+        self.fade = None;
+        self.bases.clear();
+    }
+
+    /// Mixes a second clip into the current one with a weight from 0 to 1
+    pub fn blend(&mut self, clip: &str, weight: f32) {
+        self.blended = Some(Playback {
+            clip: clip.to_string(),
+            time: 0.0,
+        });
+        self.fade = None;
+        self.bases.clear();
+        self.set_blend_weight(weight);
+    }
+
+    pub fn set_blend_weight(&mut self, weight: f32) {
+        self.weight = weight.max(0.0).min(1.0);
+    }
+
+    pub fn blend_weight(&self) -> f32 {
+        return self.weight;
+    }
+
+    /// Blends from the current clip into this one over the duration, after which only the
+    /// new clip plays
+    pub fn cross_fade(&mut self, clip: &str, duration: Duration) {
+        if self.current.is_none() {
+            self.play(clip);
+            return;
+        }
+
+        self.blend(clip, 0.0);
+        self.fade = Some(Fade {
+            elapsed: Duration::from_millis(0),
+            duration: duration,
+        });
+    }
+
+    pub fn stop(&mut self) {
+        self.current = None;
+        self.blended = None;
+        self.fade = None;
+        self.bases.clear();
+    }
+
+    pub fn current(&self) -> Option<&Playback> {
+        return self.current.as_ref();
+    }
+
+    pub fn blended(&self) -> Option<&Playback> {
+        return self.blended.as_ref();
+    }
+
+    /// The values fields had before the blended clip started mixing into them, as path,
+    /// field and value, for fields only the blended clip animates
+    pub fn blend_bases(&self) -> &[(String, String, f32)] {
+        return &self.bases;
+    }
+
+    /// Remembers the value a field had before blending, until the blend ends
+    pub fn set_blend_base(&mut self, path: &str, field: &str, value: f32) {
+        self.bases.retain(|&(ref base_path, ref base_field, _)| {
+            return base_path != path || base_field != field;
+        });
+        self.bases
+            .push((path.to_string(), field.to_string(), value));
+    }
+
+    /// Moves the clips forward. Clip lengths come from `context.animations`, so the
+    /// animation system passes in a way to look them up.
+    pub fn advance<F: Fn(&str) -> Option<f32>>(&mut self, delta: Duration, length: F) {
+        let seconds = seconds_of(delta) * self.speed;
+        let looping = self.looping;
+        for playback in self.current.iter_mut().chain(self.blended.iter_mut()) {
+            let length = length(&playback.clip).unwrap_or(0.0);
+            playback.time = wrap(playback.time + seconds, length, looping);
+        }
+
+        if let Some(mut fade) = self.fade {
+            fade.elapsed += delta;
+            if fade.elapsed >= fade.duration {
+                self.current = self.blended.take();
+                self.weight = 0.0;
+                self.fade = None;
+                self.bases.clear();
+            } else {
+                self.weight = seconds_of(fade.elapsed) / seconds_of(fade.duration);
+                self.fade = Some(fade);
+            }
+        }
+    }
+}
+
+// Keeps the time within the clip, looping around or holding at either end
+fn wrap(time: f32, length: f32, looping: bool) -> f32 {
+    if length <= 0.0 {
+        return 0.0;
+    }
+
+    if looping {
+        return time.rem_euclid(length);
+    }
+
+    return time.max(0.0).min(length);
+}
+
+fn seconds_of(duration: Duration) -> f32 {
+    return duration.as_secs() as f32 + duration.subsec_nanos() as f32 * 1e-9;
+}
+
+fn write_string(out: &mut Vec<u8>, text: &str) {
+    out.extend_from_slice(&(text.len() as u32).to_le_bytes());
+    out.extend_from_slice(text.as_bytes());
+}
+
+fn nanos_of(duration: Duration) -> u64 {
+    return duration.as_secs() * 1_000_000_000 + duration.subsec_nanos() as u64;
+}
+
+fn duration_of(nanos: u64) -> Duration {
+    return Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32);
+}
+
+// Reads back what `save_state` wrote, returning None once the data runs out
+struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    fn take(&mut self, length: usize) -> Option<&'a [u8]> {
+        if self.data.len() < length {
+            return None;
+        }
+
+        let (bytes, rest) = self.data.split_at(length);
+        self.data = rest;
+        return Some(bytes);
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        return self.take(1).map(|bytes| bytes[0]);
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        return Some(u32::from_le_bytes(bytes));
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        return Some(u64::from_le_bytes(bytes));
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        return self.u32().map(f32::from_bits);
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let length = self.u32()? as usize;
+        return Some(String::from_utf8_lossy(self.take(length)?).into_owned());
+    }
+
+    // An optional playback, so the outer None means the data ran out
+    fn playback(&mut self) -> Option<Option<Playback>> {
+        if self.u8()? == 0 {
+            return Some(None);
+        }
+
+        let clip = self.string()?;
+        let time = self.f32()?;
+        return Some(Some(Playback {
+            clip: clip,
+            time: time,
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_state_restores_clips_fade_and_bases() {
+        let mut animator = Animator::new();
+        animator.play("walk");
+        animator.advance(Duration::from_millis(250), |_| Some(1.0));
+        animator.cross_fade("run", Duration::from_secs(1));
+        animator.advance(Duration::from_millis(500), |_| Some(1.0));
+        animator.looping = false;
+        animator.set_domain(TimeDomain::Ui);
+        animator.set_blend_base(".", "x", 4.0);
+
+        let mut state = Vec::new();
+        animator.save_state(&mut state);
+
+        animator.play("jump");
+        animator.looping = true;
+        animator.load_state(&state);
+
+        assert_eq!(animator.current().unwrap().clip, "walk");
+        assert_eq!(animator.current().unwrap().time, 0.75);
+        assert_eq!(animator.blended().unwrap().clip, "run");
+        assert_eq!(animator.blend_weight(), 0.5);
+        assert!(!animator.looping);
+        assert_eq!(animator.domain(), TimeDomain::Ui);
+        assert_eq!(
+            animator.blend_bases()[0],
+            (".".to_string(), "x".to_string(), 4.0)
+        );
+
+        // The restored fade finishes where the saved one would have
+        animator.advance(Duration::from_millis(500), |_| Some(1.0));
+        assert_eq!(animator.current().unwrap().clip, "run");
+        assert!(animator.blended().is_none());
+    }
+}
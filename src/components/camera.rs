@@ -0,0 +1,119 @@
+use components::Component;
+use context::camera::Camera;
+use context::follow::CameraFollow;
+use std::fmt;
+
+/// Every layer, see `Renderable::layer`
+pub const ALL_LAYERS: u32 = ::std::u32::MAX;
+
+/// Whether the layer is one of the mask's, one bit per layer. Layers from 32 on are in no
+/// mask, so renderables on them are never drawn.
+pub fn mask_has_layer(layer_mask: u32, layer: u8) -> bool {
+    return layer < 32 && layer_mask & (1 << layer) != 0;
+}
+
+/// A part of the window as fractions of its size, from the top left
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NormalizedRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Makes its entity a camera. The renderer draws the scene once for every camera entity
+/// in order of priority, each into its own part of the window, which makes split screen,
+/// minimaps and picture-in-picture possible. Without camera entities it draws once with
+/// `context.camera`.
+pub struct CameraView {
+    pub camera: Camera,
+    /// The part of the window to draw into, the whole window by default
+    pub viewport: NormalizedRect,
+    /// The color the viewport is cleared to first, or None to draw over the cameras
+    /// before this one
+    pub clear_color: Option<[f32; 3]>,
+    /// Cameras with higher priorities are drawn later, on top of the others
+    pub priority: i32,
+    /// The layers to draw, one bit per layer
+    pub layer_mask: u32,
+    /// Moves the camera after a target, like the `CameraFollow` resource does for
+    /// `context.camera`
+    pub follow: Option<CameraFollow>,
+}
+
+impl fmt::Debug for CameraView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return f
+            .debug_struct("CameraView")
+            .field("center", &self.camera.center())
+            .field("viewport", &self.viewport)
+            .field("priority", &self.priority)
+            .field("layer_mask", &self.layer_mask)
+            .finish();
+    }
+}
+
+impl Component for CameraView {
+    fn name(&self) -> &'static str {
+        return CameraView::name();
+    }
+}
+
+impl CameraView {
+    pub fn name() -> &'static str {
+        return "camera";
+    }
+
+    pub fn new() -> Box<CameraView> {
+        let view = CameraView {
+            camera: Camera::new(),
+            viewport: NormalizedRect {
+                x: 0.0,
+                y: 0.0,
+                width: 1.0,
+                height: 1.0,
+            },
+            clear_color: Some([0.0, 0.0, 0.0]),
+            priority: 0,
+            layer_mask: ALL_LAYERS,
+            follow: None,
+        };
+        return Box::new(view);
+    }
+
+    pub fn set_viewport(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        self.viewport = NormalizedRect {
+            x: x,
+            y: y,
+            width: width,
+            height: height,
+        };
+    }
+
+    /// Whether the camera draws renderables on the layer
+    pub fn sees_layer(&self, layer: u8) -> bool {
+        return mask_has_layer(self.layer_mask, layer);
+    }
+
+    /// Fits the camera into its part of a window of this size, in physical pixels. Called
+    /// by the camera system every frame.
+    pub fn fit(&mut self, screen_width: u32, screen_height: u32) {
+        let (screen_width, screen_height) = (screen_width as f32, screen_height as f32);
+        let left = (self.viewport.x * screen_width).round() as i32;
+        let right = ((self.viewport.x + self.viewport.width) * screen_width).round() as i32;
+        let top = (self.viewport.y * screen_height).round() as i32;
+        let bottom = ((self.viewport.y + self.viewport.height) * screen_height).round() as i32;
+
+        // Regions are measured from the bottom of the window
+        let region = (
+            left,
+            screen_height as i32 - bottom,
+            (right - left).max(0) as u32,
+            (bottom - top).max(0) as u32,
+        );
+        if self.camera.region() != region {
+            self.camera
+                .set_region(region.0, region.1, region.2, region.3);
+        }
+    }
+}
@@ -1,8 +1,36 @@
+//! Components hold the data of entities, such as where and how they are drawn.
+
+pub mod animator;
+pub mod camera;
 pub mod renderable;
 
 use std::any::Any;
 use std::fmt;
 
-pub trait Component: fmt::Debug + Any {
+/// Lets entities hand out their components as concrete types
+pub trait AsAny {
+    fn as_any(&self) -> &Any;
+    fn as_any_mut(&mut self) -> &mut Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &Any {
+        return self;
+    }
+
+    fn as_any_mut(&mut self) -> &mut Any {
+        return self;
+    }
+}
+
+/// Data attached to an entity, looked up by its name
+pub trait Component: fmt::Debug + AsAny {
     fn name(&self) -> &'static str;
+
+    /// Writes the state the simulation depends on. It is used to compare the world between
+    /// runs, so components that don't affect gameplay can leave it empty.
+    fn save_state(&self, _out: &mut Vec<u8>) {}
+
+    /// Reads back what `save_state` wrote, used when rewinding
+    fn load_state(&mut self, _data: &[u8]) {}
 }
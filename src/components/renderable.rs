@@ -13,12 +13,54 @@ pub struct Renderable {
     pub z: f32,
     pub width: f32,
     pub height: f32,
+    pub visible: bool,
+    /// Multiplied with the quad's colors, white leaves them as they are
+    pub color: [f32; 3],
+    /// Which of the 32 layers, 0 to 31, the quad is on. Cameras only draw the layers in
+    /// their mask, and quads on higher layers aren't drawn at all.
+    pub layer: u8,
 }
 
 impl Component for Renderable {
     fn name(&self) -> &'static str {
         return Renderable::name();
     }
+
+    fn save_state(&self, out: &mut Vec<u8>) {
+        for value in &[self.x, self.y, self.z, self.width, self.height] {
+            out.extend_from_slice(&value.to_bits().to_le_bytes());
+        }
+        out.push(self.visible as u8);
+        for value in &self.color {
+            out.extend_from_slice(&value.to_bits().to_le_bytes());
+        }
+        out.push(self.layer);
+    }
+
+    fn load_state(&mut self, data: &[u8]) {
+        if data.len() < 34 {
+            return;
+        }
+
+        let mut values = [0.0; 8];
+        for (index, value) in values.iter_mut().enumerate() {
+            let mut bits = [0; 4];
+            // The visible flag sits between the size and the color
+            let offset = if index < 5 { index * 4 } else { index * 4 + 1 };
+            bits.copy_from_slice(&data[offset..offset + 4]);
+            *value = f32::from_bits(u32::from_le_bytes(bits));
+        }
+
+        self.x = values[0];
+        self.y = values[1];
+        self.z = values[2];
+        self.width = values[3];
+        self.height = values[4];
+        self.visible = data[20] != 0;
+        self.color = [values[5], values[6], values[7]];
+        self.layer = data[33];
+        self.dirty = true;
+    }
 }
 
 impl Renderable {
@@ -37,6 +79,9 @@ impl Renderable {
             z: 0.0,
             width: 0.0,
             height: 0.0,
+            visible: true,
+            color: [1.0, 1.0, 1.0],
+            layer: 0,
         };
         return Box::new(renderable);
     }
@@ -53,6 +98,22 @@ impl Renderable {
         self.height = height;
         self.dirty = true;
     }
+
+    pub fn set_color(&mut self, red: f32, green: f32, blue: f32) {
+        self.color = [red, green, blue];
+        self.dirty = true;
+    }
+
+    pub fn set_layer(&mut self, layer: u8) {
+        self.layer = layer;
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        if self.visible != visible {
+            self.visible = visible;
+            self.dirty = true;
+        }
+    }
 }
 
 #[derive(Eq, Debug, Clone)]
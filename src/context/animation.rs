@@ -0,0 +1,431 @@
+use components::renderable::Renderable;
+use context::tween::FieldAccessor;
+use entities::Entity;
+use error::EngineError;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::str::SplitWhitespace;
+use util::lines::parse;
+
+const HEADER: &'static str = "violetta-clip 1";
+
+/// The path of tracks that animate the entity playing the clip rather than one of its
+/// children
+pub const SELF_PATH: &'static str = ".";
+
+/// How a track gets from one keyframe to the next
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    Linear,
+    /// Holds the keyframe's value until the next keyframe
+    Step,
+    /// A cubic bezier easing curve from (0, 0) to (1, 1) through two control points, the
+    /// same as CSS `cubic-bezier`
+    Bezier(f32, f32, f32, f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    /// Seconds from the start of the clip
+    pub time: f32,
+    pub value: f32,
+    /// How to move towards the next keyframe
+    pub interpolation: Interpolation,
+}
+
+/// Keyframes for one field of the animated entity or one of its children
+#[derive(Debug, Clone)]
+pub struct Track {
+    /// `SELF_PATH` or the name a child was bound under in the `Animator`
+    pub path: String,
+    pub field: String,
+    keyframes: Vec<Keyframe>,
+}
+
+impl Track {
+    pub fn new(path: &str, field: &str) -> Track {
+        return Track {
+            path: path.to_string(),
+            field: field.to_string(),
+            keyframes: Vec::new(),
+        };
+    }
+
+    /// Adds a keyframe, keeping them sorted by time
+    pub fn add_keyframe(&mut self, keyframe: Keyframe) {
+        let index = self
+            .keyframes
+            .iter()
+            .position(|existing| existing.time > keyframe.time)
+            .unwrap_or(self.keyframes.len());
+        self.keyframes.insert(index, keyframe);
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        return &self.keyframes;
+    }
+
+    /// The track's value at the time. Before the first keyframe and after the last one
+    /// the track holds their values.
+    pub fn sample(&self, time: f32) -> Option<f32> {
+        let next = match self.keyframes.iter().position(|key| key.time > time) {
+            Some(0) => return self.keyframes.first().map(|key| key.value),
+            Some(next) => next,
+            None => return self.keyframes.last().map(|key| key.value),
+        };
+
+        let from = &self.keyframes[next - 1];
+        let to = &self.keyframes[next];
+        let progress = (time - from.time) / (to.time - from.time);
+        let eased = match from.interpolation {
+            Interpolation::Linear => progress,
+            Interpolation::Step => 0.0,
+            Interpolation::Bezier(x1, y1, x2, y2) => bezier(progress, x1, y1, x2, y2),
+        };
+
+        return Some(from.value + (to.value - from.value) * eased);
+    }
+}
+
+/// A named set of tracks played together by an `Animator`
+#[derive(Debug, Clone)]
+pub struct Clip {
+    pub name: String,
+    /// Seconds until the clip ends or loops
+    pub length: f32,
+    pub tracks: Vec<Track>,
+}
+
+impl Clip {
+    pub fn new(name: &str) -> Clip {
+        return Clip {
+            name: name.to_string(),
+            length: 0.0,
+            tracks: Vec::new(),
+        };
+    }
+
+    /// Reads clips from text. A file can hold several clips, each made up of tracks of
+    /// keyframes, one per line as time, value and how to move to the next keyframe:
+    ///
+    /// ```text
+    /// violetta-clip 1
+    /// clip wave
+    /// length 2
+    /// track . y
+    /// 0 100 bezier 0.42 0 0.58 1
+    /// 1 150 linear
+    /// 2 100
+    /// track arm red
+    /// 0 1 step
+    /// 1 0.5
+    /// ```
+    ///
+    /// Tracks name the entity they animate, `.` for the one playing the clip, and one of
+    /// its fields. Without a `length` line a clip ends at its last keyframe.
+    pub fn parse(text: &str) -> Result<Vec<Clip>, EngineError> {
+        let mut clips: Vec<Clip> = Vec::new();
+        let mut explicit_length = false;
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let error = |message: &str| EngineError::Clip {
+                line: line_number,
+                message: message.to_string(),
+            };
+
+            if line_number == 1 {
+                if line.trim() != HEADER {
+                    return Err(error("not an animation clip file"));
+                }
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let first = match words.next() {
+                Some(first) => first,
+                None => continue,
+            };
+
+            if first == "clip" {
+                finish_clip(clips.last_mut(), explicit_length);
+                let name = words.next().ok_or_else(|| error("missing clip name"))?;
+                clips.push(Clip::new(name));
+                explicit_length = false;
+                continue;
+            }
+
+            let clip = clips
+                .last_mut()
+                .ok_or_else(|| error("expected a clip line"))?;
+            match first {
+                "length" => {
+                    clip.length = parse(&mut words).ok_or_else(|| error("bad length"))?;
+                    explicit_length = true;
+                }
+                "track" => {
+                    let path = words.next().ok_or_else(|| error("missing track path"))?;
+                    let field = words.next().ok_or_else(|| error("missing track field"))?;
+                    clip.tracks.push(Track::new(path, field));
+                }
+                time => {
+                    let track = clip
+                        .tracks
+                        .last_mut()
+                        .ok_or_else(|| error("expected a track line"))?;
+                    let time = time.parse().map_err(|_| error("unknown line"))?;
+                    let value = parse(&mut words).ok_or_else(|| error("bad value"))?;
+                    let interpolation = parse_interpolation(&mut words)
+                        .ok_or_else(|| error("bad interpolation"))?;
+                    track.add_keyframe(Keyframe {
+                        time: time,
+                        value: value,
+                        interpolation: interpolation,
+                    });
+                }
+            }
+        }
+
+        finish_clip(clips.last_mut(), explicit_length);
+        return Ok(clips);
+    }
+
+    /// The value of every track at the time, as path, field and value
+    pub fn sample(&self, time: f32) -> Vec<(&str, &str, f32)> {
+        return self
+            .tracks
+            .iter()
+            .filter_map(|track| {
+                track
+                    .sample(time)
+                    .map(|value| (track.path.as_str(), track.field.as_str(), value))
+            })
+            .collect();
+    }
+}
+
+/// The clips animators can play and the fields their tracks can animate. Tracks can use
+/// the renderable's `x`, `y`, `z`, `width`, `height`, `red`, `green` and `blue` and any
+/// field registered with `register_field`.
+pub struct Animations {
+    clips: HashMap<String, Clip>,
+    fields: HashMap<String, FieldAccessor>,
+}
+
+impl Animations {
+    pub fn new() -> Animations {
+        return Animations {
+            clips: HashMap::new(),
+            fields: HashMap::new(),
+        };
+    }
+
+    /// Loads the clips in a file, see `Clip::parse`, returning their names
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<String>, EngineError> {
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+
+        let clips = Clip::parse(&text)?;
+        let names = clips.iter().map(|clip| clip.name.clone()).collect();
+        for clip in clips {
+            self.add_clip(clip);
+        }
+
+        return Ok(names);
+    }
+
+    /// Adds the clip, replacing any clip with the same name
+    pub fn add_clip(&mut self, clip: Clip) {
+        self.clips.insert(clip.name.clone(), clip);
+    }
+
+    pub fn clip(&self, name: &str) -> Option<&Clip> {
+        return self.clips.get(name);
+    }
+
+    /// Lets tracks animate a component field under the name
+    pub fn register_field(&mut self, name: &str, accessor: FieldAccessor) {
+        self.fields.insert(name.to_string(), accessor);
+    }
+
+    /// The value of a field of the entity, if it has such a field
+    pub fn read_field(&self, entity: &mut Entity, field: &str) -> Option<f32> {
+        return self.field(entity, field).map(|value| *value);
+    }
+
+    /// Sets a field of the entity, returning false if it has no such field
+    pub fn write_field(&self, entity: &mut Entity, field: &str, value: f32) -> bool {
+        match self.field(entity, field) {
+            Some(target) => *target = value,
+            None => return false,
+        }
+
+        if !self.fields.contains_key(field) {
+            if let Some(renderable) = entity.get_component::<Renderable>(Renderable::name()) {
+                renderable.dirty = true;
+            }
+        }
+
+        return true;
+    }
+
+    fn field<'a>(&self, entity: &'a mut Entity, field: &str) -> Option<&'a mut f32> {
+        if let Some(accessor) = self.fields.get(field) {
+            return accessor(entity);
+        }
+
+        let renderable = entity.get_component::<Renderable>(Renderable::name())?;
+        return match field {
+            "x" => Some(&mut renderable.x),
+            "y" => Some(&mut renderable.y),
+            "z" => Some(&mut renderable.z),
+            "width" => Some(&mut renderable.width),
+            "height" => Some(&mut renderable.height),
+            "red" => Some(&mut renderable.color[0]),
+            "green" => Some(&mut renderable.color[1]),
+            "blue" => Some(&mut renderable.color[2]),
+            _ => None,
+        };
+    }
+}
+
+fn finish_clip(clip: Option<&mut Clip>, explicit_length: bool) {
+    if let Some(clip) = clip {
+        if !explicit_length {
+            clip.length = clip
+                .tracks
+                .iter()
+                .filter_map(|track| track.keyframes.last())
+                .fold(0.0, |length, key| key.time.max(length));
+        }
+    }
+}
+
+// Keyframes without an interpolation are linear
+fn parse_interpolation(words: &mut SplitWhitespace) -> Option<Interpolation> {
+    return match words.next() {
+        None | Some("linear") => Some(Interpolation::Linear),
+        Some("step") => Some(Interpolation::Step),
+        Some("bezier") => Some(Interpolation::Bezier(
+            parse(words)?,
+            parse(words)?,
+            parse(words)?,
+            parse(words)?,
+        )),
+        Some(_) => None,
+    };
+}
+
+// Finds the curve's y where its x is the progress
+fn bezier(progress: f32, x1: f32, y1: f32, x2: f32, y2: f32) -> f32 {
+    let curve = |s: f32, p1: f32, p2: f32| {
+        let inverse = 1.0 - s;
+        return 3.0 * inverse * inverse * s * p1 + 3.0 * inverse * s * s * p2 + s * s * s;
+    };
+
+    // x grows with s as long as the control points' x stay within 0 to 1, so bisection
+    // always finds it
+    let (x1, x2) = (x1.max(0.0).min(1.0), x2.max(0.0).min(1.0));
+    let (mut low, mut high) = (0.0, 1.0);
+    let mut s = progress;
+    for _ in 0..24 {
+        let x = curve(s, x1, x2);
+        if (x - progress).abs() < 1e-5 {
+            break;
+        }
+        if x < progress {
+            low = s;
+        } else {
+            high = s;
+        }
+        s = (low + high) / 2.0;
+    }
+
+    return curve(s, y1, y2);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::lines;
+
+    fn error_line(text: &str) -> Option<(usize, String)> {
+        return lines::error_line(Clip::parse(text));
+    }
+
+    #[test]
+    fn parses_clips_and_samples_tracks() {
+        let text = "violetta-clip 1
+clip wave
+length 2
+track . y
+0 100 bezier 0.42 0 0.58 1
+1 150 linear
+2 100
+track arm red
+0 1 step
+1 0.5
+
+clip blink
+track . blue
+0.25 0
+0.5 1
+";
+        let clips = Clip::parse(text).unwrap();
+        assert_eq!(clips.len(), 2);
+
+        let wave = &clips[0];
+        assert_eq!((wave.name.as_str(), wave.length), ("wave", 2.0));
+        assert_eq!(wave.tracks[0].sample(1.5), Some(125.0));
+        // The easing curve is symmetric, so it's halfway at the middle
+        assert!((wave.tracks[0].sample(0.5).unwrap() - 125.0).abs() < 1e-3);
+        assert_eq!(wave.tracks[1].path, "arm");
+        assert_eq!(wave.tracks[1].sample(0.9), Some(1.0));
+
+        // Without a length line the clip ends at its last keyframe
+        assert_eq!(clips[1].length, 0.5);
+        assert_eq!(clips[1].tracks[0].sample(0.0), Some(0.0));
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        assert_eq!(
+            error_line("violetta-replay 1\n"),
+            Some((1, "not an animation clip file".to_string()))
+        );
+        assert_eq!(
+            error_line("violetta-clip 1\nclip\n"),
+            Some((2, "missing clip name".to_string()))
+        );
+        assert_eq!(
+            error_line("violetta-clip 1\n\ntrack . x\n"),
+            Some((3, "expected a clip line".to_string()))
+        );
+        assert_eq!(
+            error_line("violetta-clip 1\nclip a\nlength long\n"),
+            Some((3, "bad length".to_string()))
+        );
+        assert_eq!(
+            error_line("violetta-clip 1\nclip a\ntrack .\n"),
+            Some((3, "missing track field".to_string()))
+        );
+        assert_eq!(
+            error_line("violetta-clip 1\nclip a\n0 1\n"),
+            Some((3, "expected a track line".to_string()))
+        );
+        assert_eq!(
+            error_line("violetta-clip 1\nclip a\ntrack . x\nspin 1\n"),
+            Some((4, "unknown line".to_string()))
+        );
+        assert_eq!(
+            error_line("violetta-clip 1\nclip a\ntrack . x\n0\n"),
+            Some((4, "bad value".to_string()))
+        );
+        assert_eq!(
+            error_line("violetta-clip 1\nclip a\ntrack . x\n0 1 bezier 0 0 1\n"),
+            Some((4, "bad interpolation".to_string()))
+        );
+    }
+}
@@ -1,12 +1,64 @@
+use context::screen::Screen;
+use std::cmp;
 use util::matrix;
 
+// Zooming out further would make the view matrix degenerate
+const MIN_ZOOM: f32 = 0.001;
+// The same goes for smaller virtual resolutions
+const MIN_VIRTUAL_SIZE: f32 = 0.001;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScalingPolicy {
+    /// The camera rectangle is set by hand with `set_viewport` and left alone on resize
+    Manual,
+    /// The virtual resolution is stretched to fill the window, ignoring the aspect ratio
+    Stretch { width: f32, height: f32 },
+    /// The virtual resolution is scaled uniformly to fit the window, with bars around it
+    Letterbox { width: f32, height: f32 },
+    /// The virtual resolution is the smallest visible area. The window shows more of the
+    /// world along whichever axis has room to spare.
+    Expand { width: f32, height: f32 },
+    /// One world unit is one physical pixel
+    Native,
+    /// The scene is drawn at exactly this many pixels and then upscaled to the window by
+    /// the largest whole factor that fits. Intended for pixel art.
+    PixelPerfect { width: u32, height: u32 },
+}
+
+/// The region of the window the camera draws into, in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Looks at the world from a center point, with a zoom and a rotation. The scaling policy
+/// decides how much of the world is visible at zoom 1.
 pub struct Camera {
+    /// The visible rectangle at the current zoom, before rotating
     pub left: f32,
     pub top: f32,
     pub right: f32,
     pub bottom: f32,
     pub view_matrix: Box<matrix::Mat4>,
     pub projection_matrix: Box<matrix::Mat4>,
+    pub viewport: Viewport,
+
+    scaling_policy: ScalingPolicy,
+    // The part of the window the camera draws into. The scaling policy fits the world
+    // into it as if it were the whole window.
+    origin: (i32, i32),
+    screen_width: u32,
+    screen_height: u32,
+
+    // The rectangle from the scaling policy or `set_viewport`, at zoom 1
+    rect: (f32, f32, f32, f32),
+    // Where the camera was moved to, otherwise it looks at the middle of `rect`
+    center: Option<(f32, f32)>,
+    zoom: f32,
+    rotation: f32,
 }
 
 impl Camera {
@@ -19,26 +71,457 @@ impl Camera {
             bottom: -1.0,
             view_matrix: Box::new(matrix::identity()),
             projection_matrix: Box::new(projection),
+            viewport: Viewport {
+                x: 0,
+                y: 0,
+                width: 0,
+                height: 0,
+            },
+
+            scaling_policy: ScalingPolicy::Native,
+            origin: (0, 0),
+            screen_width: 0,
+            screen_height: 0,
+
+            rect: (-1.0, 1.0, 1.0, -1.0),
+            center: None,
+            zoom: 1.0,
+            rotation: 0.0,
+        };
+    }
+
+    pub fn scaling_policy(&self) -> ScalingPolicy {
+        return self.scaling_policy;
+    }
+
+    /// Virtual resolutions are at least one pixel, or a thousandth of a world unit for the
+    /// policies that take fractional sizes, in each direction
+    pub fn set_scaling_policy(&mut self, scaling_policy: ScalingPolicy) {
+        self.scaling_policy = match scaling_policy {
+            ScalingPolicy::Stretch { width, height } => ScalingPolicy::Stretch {
+                width: width.max(MIN_VIRTUAL_SIZE),
+                height: height.max(MIN_VIRTUAL_SIZE),
+            },
+            ScalingPolicy::Letterbox { width, height } => ScalingPolicy::Letterbox {
+                width: width.max(MIN_VIRTUAL_SIZE),
+                height: height.max(MIN_VIRTUAL_SIZE),
+            },
+            ScalingPolicy::Expand { width, height } => ScalingPolicy::Expand {
+                width: width.max(MIN_VIRTUAL_SIZE),
+                height: height.max(MIN_VIRTUAL_SIZE),
+            },
+            ScalingPolicy::PixelPerfect { width, height } => ScalingPolicy::PixelPerfect {
+                width: width.max(1),
+                height: height.max(1),
+            },
+            other => other,
         };
+        self.apply_scaling_policy();
+    }
+
+    /// Called by the game runner whenever the window size changes
+    pub fn resize(&mut self, screen_width: u32, screen_height: u32) {
+        self.set_region(0, 0, screen_width, screen_height);
+    }
+
+    /// Draws into part of the window instead of all of it, e.g. for split screen. The
+    /// region is in physical pixels from the bottom left of the window, like `Viewport`.
+    pub fn set_region(&mut self, x: i32, y: i32, width: u32, height: u32) {
+        self.origin = (x, y);
+        self.screen_width = width;
+        self.screen_height = height;
+        self.apply_scaling_policy();
+    }
+
+    /// The part of the window the camera draws into as x, y, width and height
+    pub fn region(&self) -> (i32, i32, u32, u32) {
+        return (
+            self.origin.0,
+            self.origin.1,
+            self.screen_width,
+            self.screen_height,
+        );
+    }
+
+    /// The top left corner of the visible rectangle, before rotating
+    pub fn position(&self) -> (f32, f32) {
+        return (self.left, self.top);
+    }
+
+    /// Moves the camera so the top left corner of the visible rectangle is at the point
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        let (width, height) = self.visible_size();
+        self.set_center(x + width / 2.0, y + height / 2.0);
     }
 
+    /// Sets the visible rectangle at zoom 1 and looks at its middle. This switches to the
+    /// manual scaling policy.
     pub fn set_viewport(&mut self, left: f32, top: f32, right: f32, bottom: f32) {
-        self.left = left;
-        self.top = top;
-        self.right = right;
-        self.bottom = bottom;
+        self.scaling_policy = ScalingPolicy::Manual;
+        self.center = None;
+        self.set_rect(left, top, right, bottom);
+    }
+
+    /// The point in the world the camera looks at
+    pub fn center(&self) -> (f32, f32) {
+        let (left, top, right, bottom) = self.rect;
+        return self
+            .center
+            .unwrap_or(((left + right) / 2.0, (top + bottom) / 2.0));
+    }
+
+    /// Moves the camera to look at the point. It stays there when the window is resized.
+    pub fn set_center(&mut self, x: f32, y: f32) {
+        self.center = Some((x, y));
+        self.update_view();
+    }
+
+    pub fn zoom(&self) -> f32 {
+        return self.zoom;
+    }
+
+    /// Magnifies the view around the center, 2 shows half as much of the world
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.max(MIN_ZOOM);
+        self.update_view();
+    }
 
-        let half_width = (right - left) / 2.0;
-        let center_x = left + half_width;
+    /// The camera's rotation in radians
+    pub fn rotation(&self) -> f32 {
+        return self.rotation;
+    }
 
-        let half_height = (bottom - top) / 2.0;
-        let center_y = top + half_height;
+    /// Tilts the camera around its center. The world appears turned the other way.
+    pub fn set_rotation(&mut self, radians: f32) {
+        self.rotation = radians;
+        self.update_view();
+    }
+
+    /// The width and height of the visible part of the world at the current zoom
+    pub fn visible_size(&self) -> (f32, f32) {
+        return (self.right - self.left, self.bottom - self.top);
+    }
+
+    /// The corners of the visible part of the world, rotated with the camera, starting at
+    /// the top left one and going around to the bottom left one
+    pub fn visible_corners(&self) -> [(f32, f32); 4] {
+        // The middle of the drawn rectangle, which may have been snapped away from `center`
+        let (center_x, center_y) = (
+            (self.left + self.right) / 2.0,
+            (self.top + self.bottom) / 2.0,
+        );
+        let (half_width, half_height) = (
+            (self.right - self.left) / 2.0,
+            (self.bottom - self.top) / 2.0,
+        );
+        let (sin, cos) = self.rotation.sin_cos();
+
+        let mut corners = [
+            (-half_width, -half_height),
+            (half_width, -half_height),
+            (half_width, half_height),
+            (-half_width, half_height),
+        ];
+        for corner in corners.iter_mut() {
+            let (x, y) = *corner;
+            *corner = (center_x + x * cos - y * sin, center_y + x * sin + y * cos);
+        }
+
+        return corners;
+    }
+
+    /// The smallest rectangle around everything visible as left, top, right and bottom.
+    /// Without rotation it's the same as the camera's `left`, `top`, `right` and `bottom`.
+    pub fn visible_rect(&self) -> (f32, f32, f32, f32) {
+        let corners = self.visible_corners();
+        let mut rect = (corners[0].0, corners[0].1, corners[0].0, corners[0].1);
+        for &(x, y) in &corners[1..] {
+            rect.0 = rect.0.min(x);
+            rect.1 = rect.1.min(y);
+            rect.2 = rect.2.max(x);
+            rect.3 = rect.3.max(y);
+        }
+
+        // Keep the rectangle the same way up as the camera's
+        if self.top > self.bottom {
+            return (rect.0, rect.3, rect.2, rect.1);
+        }
+
+        return rect;
+    }
+
+    fn apply_scaling_policy(&mut self) {
+        let screen_width = self.screen_width as f32;
+        let screen_height = self.screen_height as f32;
+        if self.screen_width == 0 || self.screen_height == 0 {
+            // Minimized windows report a zero size. Keep the last good state.
+            return;
+        }
+
+        self.viewport = Viewport {
+            x: 0,
+            y: 0,
+            width: self.screen_width as i32,
+            height: self.screen_height as i32,
+        };
+
+        match self.scaling_policy {
+            ScalingPolicy::Manual => {}
+            ScalingPolicy::Stretch { width, height } => {
+                self.set_rect(0.0, 0.0, width, height);
+            }
+            ScalingPolicy::Letterbox { width, height } => {
+                let scale = (screen_width / width).min(screen_height / height);
+                let viewport_width = (width * scale).round() as i32;
+                let viewport_height = (height * scale).round() as i32;
+                self.viewport = Viewport {
+                    x: (self.screen_width as i32 - viewport_width) / 2,
+                    y: (self.screen_height as i32 - viewport_height) / 2,
+                    width: viewport_width,
+                    height: viewport_height,
+                };
+                self.set_rect(0.0, 0.0, width, height);
+            }
+            ScalingPolicy::Expand { width, height } => {
+                let scale = (screen_width / width).min(screen_height / height);
+                let visible_width = screen_width / scale;
+                let visible_height = screen_height / scale;
+                let left = (width - visible_width) / 2.0;
+                let top = (height - visible_height) / 2.0;
+                self.set_rect(left, top, left + visible_width, top + visible_height);
+            }
+            ScalingPolicy::Native => {
+                self.set_rect(0.0, 0.0, screen_width, screen_height);
+            }
+            ScalingPolicy::PixelPerfect { width, height } => {
+                let whole_scale = cmp::min(self.screen_width / width, self.screen_height / height);
+                // Below the virtual resolution there's no whole factor that fits, so the
+                // scene is scaled down as far as needed instead
+                let scale = if whole_scale > 0 {
+                    whole_scale as f32
+                } else {
+                    (screen_width / width as f32).min(screen_height / height as f32)
+                };
+                let viewport_width = (width as f32 * scale).round() as i32;
+                let viewport_height = (height as f32 * scale).round() as i32;
+                self.viewport = Viewport {
+                    x: (self.screen_width as i32 - viewport_width) / 2,
+                    y: (self.screen_height as i32 - viewport_height) / 2,
+                    width: viewport_width,
+                    height: viewport_height,
+                };
+                self.set_rect(0.0, 0.0, width as f32, height as f32);
+            }
+        }
+
+        self.viewport.x += self.origin.0;
+        self.viewport.y += self.origin.1;
+    }
+
+    /// Where a point in the window is in the world. The point is in physical pixels from
+    /// the top left of the window, like mouse positions; logical positions can be converted
+    /// with `Screen::to_physical` first.
+    pub fn screen_to_world(&self, screen: &Screen, x: f32, y: f32) -> (f32, f32) {
+        let viewport = &self.viewport;
+        if viewport.width == 0 || viewport.height == 0 {
+            return (x, y);
+        }
+
+        // GL puts the origin of the window at the bottom left
+        let from_bottom = screen.height as f32 - y;
+        let ndc_x = (x - viewport.x as f32) / viewport.width as f32 * 2.0 - 1.0;
+        let ndc_y = (from_bottom - viewport.y as f32) / viewport.height as f32 * 2.0 - 1.0;
+
+        let mut transform = self.view_projection();
+        // The view flattens depth, which would leave nothing to invert. Points on screen
+        // map to world z 0.
+        transform[8..12].copy_from_slice(&[0.0, 0.0, 1.0, 0.0]);
+        let inverse = match matrix::inverse(&transform) {
+            Some(inverse) => inverse,
+            None => return (x, y),
+        };
+
+        let world = matrix::transform(&inverse, [ndc_x, ndc_y, 0.0, 1.0]);
+        return (world[0] / world[3], world[1] / world[3]);
+    }
+
+    /// Where a point in the world is in the window, in physical pixels from the top left
+    pub fn world_to_screen(&self, screen: &Screen, x: f32, y: f32, z: f32) -> (f32, f32) {
+        let clip = matrix::transform(&self.view_projection(), [x, y, z, 1.0]);
+        let (ndc_x, ndc_y) = (clip[0] / clip[3], clip[1] / clip[3]);
+
+        let viewport = &self.viewport;
+        let screen_x = viewport.x as f32 + (ndc_x + 1.0) / 2.0 * viewport.width as f32;
+        let from_bottom = viewport.y as f32 + (ndc_y + 1.0) / 2.0 * viewport.height as f32;
+        return (screen_x, screen.height as f32 - from_bottom);
+    }
+
+    // The projection and view matrices combined, as the vertex shader applies them
+    fn view_projection(&self) -> matrix::Mat4 {
+        let mut transform = *self.projection_matrix;
+        matrix::multiply(&mut transform, &self.view_matrix);
+        return transform;
+    }
+
+    /// The offscreen resolution the scene should be drawn at, if any
+    pub fn virtual_resolution(&self) -> Option<(u32, u32)> {
+        return match self.scaling_policy {
+            ScalingPolicy::PixelPerfect { width, height } => Some((width, height)),
+            _ => None,
+        };
+    }
+
+    fn set_rect(&mut self, left: f32, top: f32, right: f32, bottom: f32) {
+        self.rect = (left, top, right, bottom);
+        self.update_view();
+    }
+
+    // Rebuilds the visible rectangle and the view matrix from the center, zoom and rotation
+    fn update_view(&mut self) {
+        let (left, top, right, bottom) = self.rect;
+        let (mut center_x, mut center_y) = self.center();
+
+        let half_width = (right - left) / 2.0 / self.zoom;
+        let half_height = (bottom - top) / 2.0 / self.zoom;
+
+        // Sprites are drawn on whole virtual pixels, so the view moves in whole virtual
+        // pixels too. Otherwise a fractional center still makes the scene shimmer.
+        if let Some((width, height)) = self.virtual_resolution() {
+            let pixel_width = half_width * 2.0 / width as f32;
+            let pixel_height = half_height * 2.0 / height as f32;
+            center_x = ((center_x - half_width) / pixel_width).round() * pixel_width + half_width;
+            center_y =
+                ((center_y - half_height) / pixel_height).round() * pixel_height + half_height;
+        }
+
+        self.left = center_x - half_width;
+        self.top = center_y - half_height;
+        self.right = center_x + half_width;
+        self.bottom = center_y + half_height;
 
         let width_scale = 1.0 / half_width;
         let height_scale = 1.0 / half_height;
 
         matrix::to_identity(self.view_matrix.as_mut());
         matrix::scale(self.view_matrix.as_mut(), width_scale, height_scale, 0.0);
+        matrix::rotate(self.view_matrix.as_mut(), 0.0, 0.0, -self.rotation);
         matrix::translate(self.view_matrix.as_mut(), -center_x, -center_y, 0.0);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use context::screen::Screen;
+
+    fn screen(width: u32, height: u32) -> Screen {
+        let mut screen = Screen::new();
+        screen.set_dimensions(width, height);
+        return screen;
+    }
+
+    #[test]
+    fn screen_center_is_camera_center() {
+        let mut camera = Camera::new();
+        camera.resize(800, 600);
+        camera.set_center(100.0, -40.0);
+        camera.set_zoom(2.0);
+        camera.set_rotation(0.7);
+
+        let (x, y) = camera.screen_to_world(&screen(800, 600), 400.0, 300.0);
+        assert!((x - 100.0).abs() < 1e-3 && (y + 40.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn screen_and_world_round_trip() {
+        let screen = screen(1280, 720);
+        let mut camera = Camera::new();
+        camera.set_scaling_policy(ScalingPolicy::Letterbox {
+            width: 320.0,
+            height: 240.0,
+        });
+        camera.resize(1280, 720);
+        camera.set_center(1000.0, 2000.0);
+        camera.set_zoom(1.5);
+        camera.set_rotation(-0.3);
+
+        for &(x, y) in &[(0.0, 0.0), (640.0, 360.0), (1279.0, 10.0), (200.0, 700.0)] {
+            let (world_x, world_y) = camera.screen_to_world(&screen, x, y);
+            let (back_x, back_y) = camera.world_to_screen(&screen, world_x, world_y, 0.0);
+            assert!((back_x - x).abs() < 0.01, "{} != {}", back_x, x);
+            assert!((back_y - y).abs() < 0.01, "{} != {}", back_y, y);
+        }
+    }
+
+    #[test]
+    fn pixel_perfect_stays_inside_a_small_region() {
+        let mut camera = Camera::new();
+        camera.set_scaling_policy(ScalingPolicy::PixelPerfect {
+            width: 320,
+            height: 180,
+        });
+        camera.set_region(100, 50, 160, 120);
+
+        let viewport = camera.viewport;
+        assert!(viewport.x >= 100 && viewport.y >= 50);
+        assert!(viewport.x + viewport.width <= 260);
+        assert!(viewport.y + viewport.height <= 170);
+        assert_eq!((viewport.width, viewport.height), (160, 90));
+    }
+
+    #[test]
+    fn pixel_perfect_views_move_in_whole_virtual_pixels() {
+        let mut camera = Camera::new();
+        camera.set_scaling_policy(ScalingPolicy::PixelPerfect {
+            width: 321,
+            height: 180,
+        });
+        camera.resize(642, 360);
+
+        camera.set_center(10.3, 20.6);
+        assert_eq!(camera.position(), (-150.0, -69.0));
+        assert_eq!(camera.center(), (10.3, 20.6));
+
+        // Zoomed in, a virtual pixel covers less of the world
+        camera.set_zoom(2.0);
+        let (left, top) = camera.position();
+        assert_eq!(((left * 2.0).fract(), (top * 2.0).fract()), (0.0, 0.0));
+    }
+
+    #[test]
+    fn empty_virtual_resolutions_are_clamped() {
+        let policies = [
+            ScalingPolicy::Stretch {
+                width: 0.0,
+                height: 240.0,
+            },
+            ScalingPolicy::Letterbox {
+                width: 320.0,
+                height: 0.0,
+            },
+            ScalingPolicy::Expand {
+                width: 0.0,
+                height: -1.0,
+            },
+            ScalingPolicy::PixelPerfect {
+                width: 0,
+                height: 0,
+            },
+        ];
+
+        for &policy in &policies {
+            let mut camera = Camera::new();
+            camera.set_scaling_policy(policy);
+            camera.resize(800, 600);
+
+            let viewport = camera.viewport;
+            assert!(viewport.width >= 0 && viewport.height >= 0, "{:?}", policy);
+            assert!(
+                camera.view_matrix.iter().all(|value| value.is_finite()),
+                "{:?}",
+                policy
+            );
+        }
+    }
+}
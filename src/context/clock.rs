@@ -1,53 +1,158 @@
 use std::ops::Add;
-use std::ops::Sub;
 use std::time::Duration;
 use std::time::Instant;
 
+const DEFAULT_MAX_DELTA_MS: u64 = 100;
+// Real time follows the wall clock, hitches included
+const REAL_MAX_DELTA_SECS: u64 = 60 * 60;
+
+/// Which clock drives something that changes over time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeDomain {
+    /// Wall clock time. The engine never pauses or scales it.
+    Real,
+    /// Menus and HUD, which keep moving while the game is paused
+    Ui,
+    /// The simulation, paused when the game is and affected by slow motion
+    Game,
+}
+
+/// Time in one domain, advanced once per frame by the time system so everything sees the
+/// same values during a frame. Each clock has its own pause state and time scale.
 pub struct Clock {
-    start: Instant,
-    pause_duration: Duration,
-    time_of_pause: Option<Instant>,
+    last_tick: Option<Instant>,
+    paused: bool,
+    time_scale: f32,
+    // Longer frames are cut short so a hitch doesn't make the game jump ahead
+    max_delta: Duration,
+
+    // In fixed timestep mode game time advances by exactly one step per frame, which
+    // makes runs reproducible
+    fixed_timestep: Option<Duration>,
+
+    frame: u64,
+    delta: Duration,
+    unscaled_delta: Duration,
+    game_time: Duration,
 }
 
 impl Clock {
     pub fn new() -> Clock {
         return Clock {
-            start: Instant::now(),
-            pause_duration: Duration::from_millis(0),
-            time_of_pause: None,
+            last_tick: None,
+            paused: false,
+            time_scale: 1.0,
+            max_delta: Duration::from_millis(DEFAULT_MAX_DELTA_MS),
+
+            fixed_timestep: None,
+
+            frame: 0,
+            delta: Duration::from_millis(0),
+            unscaled_delta: Duration::from_millis(0),
+            game_time: Duration::from_millis(0),
+        };
+    }
+
+    /// A clock for `TimeDomain::Real`, which isn't clamped after long frames
+    pub fn real() -> Clock {
+        let mut clock = Clock::new();
+        clock.set_max_delta(Duration::from_secs(REAL_MAX_DELTA_SECS));
+        return clock;
+    }
+
+    pub fn fixed_timestep(&self) -> Option<Duration> {
+        return self.fixed_timestep;
+    }
+
+    pub fn set_fixed_timestep(&mut self, fixed_timestep: Option<Duration>) {
+        self.fixed_timestep = fixed_timestep;
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        return self.time_scale;
+    }
+
+    /// Multiplies how fast game time passes, e.g. 0.5 for slow motion or 2 to fast-forward
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    pub fn max_delta(&self) -> Duration {
+        return self.max_delta;
+    }
+
+    /// The longest a single frame may advance time, 100ms by default
+    pub fn set_max_delta(&mut self, max_delta: Duration) {
+        self.max_delta = max_delta;
+    }
+
+    /// Called by the time system once at the start of every frame
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        let elapsed = match self.last_tick {
+            Some(last_tick) => now.duration_since(last_tick),
+            None => Duration::from_millis(0),
+        };
+        self.last_tick = Some(now);
+
+        self.unscaled_delta = match self.fixed_timestep {
+            Some(timestep) => timestep,
+            None => elapsed.min(self.max_delta),
+        };
+        self.delta = if self.paused {
+            Duration::from_millis(0)
+        } else {
+            scale(self.unscaled_delta, self.time_scale)
         };
+
+        self.game_time = self.game_time.add(self.delta);
+        self.frame += 1;
+    }
+
+    /// How far game time moved this frame, scaled and zero while paused
+    pub fn delta(&self) -> Duration {
+        return self.delta;
+    }
+
+    /// `delta` in seconds, which is what movement code usually wants
+    pub fn delta_seconds(&self) -> f32 {
+        return self.delta.as_secs() as f32 + self.delta.subsec_nanos() as f32 * 1e-9;
+    }
+
+    /// How long this frame took, ignoring pause and the time scale
+    pub fn unscaled_delta(&self) -> Duration {
+        return self.unscaled_delta;
+    }
+
+    /// The number of frames ticked so far
+    pub fn frame(&self) -> u64 {
+        return self.frame;
+    }
+
+    /// Moves game time to the given point, e.g. when rewinding
+    pub fn set_game_time(&mut self, time: Duration) {
+        self.game_time = time;
     }
 
     pub fn pause(&mut self) {
-        match self.time_of_pause {
-            Some(_) => return,
-            None => {
-                self.time_of_pause = Some(Instant::now());
-            }
-        }
+        self.paused = true;
     }
 
     pub fn resume(&mut self) {
-        if let Some(instant) = self.time_of_pause {
-            let pause_duration = Instant::now().duration_since(instant);
-            self.pause_duration = self.pause_duration.add(pause_duration);
-            self.time_of_pause = None;
-        }
+        self.paused = false;
     }
 
     pub fn is_paused(&self) -> bool {
-        return self.time_of_pause.is_some();
+        return self.paused;
     }
 
     pub fn game_time(&self) -> Duration {
-        let now = Instant::now();
-        let mut duration = now.duration_since(self.start).sub(self.pause_duration);
-
-        if let Some(instant) = self.time_of_pause {
-            let pause_duration = now.duration_since(instant);
-            duration = duration.sub(pause_duration);
-        }
-
-        return duration;
+        return self.game_time;
     }
 }
+
+fn scale(duration: Duration, factor: f32) -> Duration {
+    let nanos = duration.as_secs() as f64 * 1e9 + duration.subsec_nanos() as f64;
+    let scaled = (nanos * factor as f64) as u64;
+    return Duration::new(scaled / 1_000_000_000, (scaled % 1_000_000_000) as u32);
+}
@@ -0,0 +1,74 @@
+use std::slice;
+
+/// A queue of events of one type. Events stay readable for the frame they were sent in and
+/// the one after, so everything that runs once per frame sees them no matter the order.
+pub struct Events<T> {
+    previous: Vec<T>,
+    current: Vec<T>,
+    // How many events were sent before the first one in `previous`
+    previous_start: u64,
+}
+
+/// Remembers which events a reader has seen, so that each one is read exactly once
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EventReader {
+    next: u64,
+}
+
+impl EventReader {
+    pub fn new() -> EventReader {
+        return EventReader { next: 0 };
+    }
+}
+
+impl<T> Events<T> {
+    pub fn new() -> Events<T> {
+        return Events {
+            previous: Vec::new(),
+            current: Vec::new(),
+            previous_start: 0,
+        };
+    }
+
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+    }
+
+    /// Every readable event, oldest first
+    pub fn iter(&self) -> ::std::iter::Chain<slice::Iter<'_, T>, slice::Iter<'_, T>> {
+        return self.previous.iter().chain(self.current.iter());
+    }
+
+    /// The readable events the reader hasn't seen yet, oldest first
+    pub fn read(&self, reader: &mut EventReader) -> Vec<&T> {
+        let skip = reader.next.saturating_sub(self.previous_start) as usize;
+        let unread: Vec<&T> = self.iter().skip(skip).collect();
+
+        reader.next = self.previous_start + (self.previous.len() + self.current.len()) as u64;
+        return unread;
+    }
+
+    /// A reader that skips every event sent so far
+    pub fn reader_at_end(&self) -> EventReader {
+        return EventReader {
+            next: self.previous_start + (self.previous.len() + self.current.len()) as u64,
+        };
+    }
+
+    /// Only the events sent since the start of this frame
+    pub fn this_frame(&self) -> &[T] {
+        return &self.current;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.previous.is_empty() && self.current.is_empty();
+    }
+
+    /// Called by the game runner at the start of every frame, drops the events of the
+    /// frame before last
+    pub fn update(&mut self) {
+        self.previous_start += self.previous.len() as u64;
+        self.previous.clear();
+        ::std::mem::swap(&mut self.previous, &mut self.current);
+    }
+}
@@ -0,0 +1,246 @@
+use context::camera::Camera;
+use context::generational::Key;
+
+// How quickly the estimated target velocity catches up, per second
+const LOOK_AHEAD_RESPONSE: f32 = 8.0;
+
+/// Moves the camera after a target entity's renderable. Insert it as a resource and the
+/// camera system updates `context.camera` with it every frame, after the game loop and
+/// before rendering.
+///
+/// The target can move freely within the dead zone around the center of the view. Once it
+/// leaves it the camera follows, aiming ahead of where the target is heading and easing
+/// in without overshooting. Bounds keep the view inside the level, and trauma shakes the
+/// camera, fading over time.
+pub struct CameraFollow {
+    pub target: Option<Key>,
+    /// Half the width and height of the area around the center the target can move
+    /// within without moving the camera
+    pub dead_zone: (f32, f32),
+    /// How many seconds of the target's velocity the camera aims ahead
+    pub look_ahead: f32,
+    /// The farthest the camera aims ahead of the target
+    pub max_look_ahead: f32,
+    /// Roughly how many seconds the camera takes to catch up, zero to stay locked on
+    pub smooth_time: f32,
+    /// The part of the world the view stays inside, as left, top, right and bottom
+    pub bounds: Option<(f32, f32, f32, f32)>,
+
+    /// How far the view moves and turns at full trauma
+    pub max_shake_offset: (f32, f32),
+    pub max_shake_angle: f32,
+    /// How fast the shake moves back and forth
+    pub shake_frequency: f32,
+    /// How much trauma fades per second
+    pub trauma_decay: f32,
+
+    // Where the camera looks without the shake
+    focus: Option<(f32, f32)>,
+    velocity: (f32, f32),
+    last_target: Option<(f32, f32)>,
+    target_velocity: (f32, f32),
+
+    trauma: f32,
+    shake_time: f32,
+    // The rotation the shake added last frame, taken off again before shaking anew
+    shake_angle: f32,
+}
+
+impl CameraFollow {
+    pub fn new(target: Key) -> CameraFollow {
+        return CameraFollow {
+            target: Some(target),
+            dead_zone: (0.0, 0.0),
+            look_ahead: 0.0,
+            max_look_ahead: 0.0,
+            smooth_time: 0.2,
+            bounds: None,
+
+            max_shake_offset: (16.0, 16.0),
+            max_shake_angle: 0.05,
+            shake_frequency: 15.0,
+            trauma_decay: 1.0,
+
+            focus: None,
+            velocity: (0.0, 0.0),
+            last_target: None,
+            target_velocity: (0.0, 0.0),
+
+            trauma: 0.0,
+            shake_time: 0.0,
+            shake_angle: 0.0,
+        };
+    }
+
+    /// Shakes the camera, e.g. 0.3 for a hit and 1 for an explosion. Trauma adds up to at
+    /// most 1 and the shake grows with its square, so small hits stay subtle.
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).max(0.0).min(1.0);
+    }
+
+    pub fn trauma(&self) -> f32 {
+        return self.trauma;
+    }
+
+    /// Jumps straight to the target on the next update instead of easing there, e.g.
+    /// after a teleport
+    pub fn snap(&mut self) {
+        self.focus = None;
+        self.velocity = (0.0, 0.0);
+        self.last_target = None;
+        self.target_velocity = (0.0, 0.0);
+    }
+
+    /// Moves the camera towards the target's position, called by the camera system
+    pub fn update(&mut self, camera: &mut Camera, target: Option<(f32, f32)>, delta: f32) {
+        if let Some(target) = target {
+            self.follow(camera, target, delta);
+        }
+
+        let focus = match self.focus {
+            Some(focus) => self.clamp(camera, focus),
+            None => camera.center(),
+        };
+        self.focus = Some(focus);
+
+        self.trauma = (self.trauma - self.trauma_decay * delta).max(0.0);
+        self.shake_time += delta;
+        let shake = self.trauma * self.trauma;
+        let time = self.shake_time * self.shake_frequency;
+
+        camera.set_center(
+            focus.0 + self.max_shake_offset.0 * shake * noise(time, 0.0),
+            focus.1 + self.max_shake_offset.1 * shake * noise(time, 1.0),
+        );
+
+        let base_rotation = camera.rotation() - self.shake_angle;
+        self.shake_angle = self.max_shake_angle * shake * noise(time, 2.0);
+        camera.set_rotation(base_rotation + self.shake_angle);
+    }
+
+    fn follow(&mut self, camera: &Camera, target: (f32, f32), delta: f32) {
+        if let (Some(last), true) = (self.last_target, delta > 0.0) {
+            let velocity = ((target.0 - last.0) / delta, (target.1 - last.1) / delta);
+            // Smooth out frame to frame jitter so the look ahead doesn't twitch
+            let blend = 1.0 - (-delta * LOOK_AHEAD_RESPONSE).exp();
+            self.target_velocity.0 += (velocity.0 - self.target_velocity.0) * blend;
+            self.target_velocity.1 += (velocity.1 - self.target_velocity.1) * blend;
+        }
+        self.last_target = Some(target);
+
+        let ahead = clamp_length(
+            (
+                self.target_velocity.0 * self.look_ahead,
+                self.target_velocity.1 * self.look_ahead,
+            ),
+            self.max_look_ahead,
+        );
+        let aim = (target.0 + ahead.0, target.1 + ahead.1);
+
+        let focus = match self.focus {
+            Some(focus) => focus,
+            None => {
+                self.focus = Some(aim);
+                return;
+            }
+        };
+
+        let goal = (
+            outside_dead_zone(focus.0, aim.0, self.dead_zone.0),
+            outside_dead_zone(focus.1, aim.1, self.dead_zone.1),
+        );
+        let x = smooth_damp(
+            focus.0,
+            goal.0,
+            &mut self.velocity.0,
+            self.smooth_time,
+            delta,
+        );
+        let y = smooth_damp(
+            focus.1,
+            goal.1,
+            &mut self.velocity.1,
+            self.smooth_time,
+            delta,
+        );
+        self.focus = Some(self.clamp(camera, (x, y)));
+    }
+
+    // Keeps the view inside the bounds, centering it where the bounds are smaller
+    fn clamp(&self, camera: &Camera, focus: (f32, f32)) -> (f32, f32) {
+        let (left, top, right, bottom) = match self.bounds {
+            Some(bounds) => bounds,
+            None => return focus,
+        };
+
+        let (width, height) = camera.visible_size();
+        return (
+            clamp_axis(focus.0, left, right, width.abs() / 2.0),
+            clamp_axis(
+                focus.1,
+                top.min(bottom),
+                top.max(bottom),
+                height.abs() / 2.0,
+            ),
+        );
+    }
+}
+
+// Where the camera needs to be so the point is just inside the dead zone
+fn outside_dead_zone(center: f32, point: f32, half_size: f32) -> f32 {
+    let offset = point - center;
+    if offset > half_size {
+        return point - half_size;
+    } else if offset < -half_size {
+        return point + half_size;
+    }
+
+    return center;
+}
+
+// A critically damped spring, which eases towards the goal as fast as it can without
+// overshooting. The approximation of the exponential is from Game Programming Gems 4.
+fn smooth_damp(current: f32, goal: f32, velocity: &mut f32, smooth_time: f32, delta: f32) -> f32 {
+    if smooth_time <= 0.0 {
+        *velocity = 0.0;
+        return goal;
+    }
+
+    let omega = 2.0 / smooth_time;
+    let x = omega * delta;
+    let decay = 1.0 / (1.0 + x + 0.48 * x * x + 0.235 * x * x * x);
+
+    let change = current - goal;
+    let temp = (*velocity + omega * change) * delta;
+    *velocity = (*velocity - omega * temp) * decay;
+    return goal + (change + temp) * decay;
+}
+
+fn clamp_axis(value: f32, low: f32, high: f32, half_size: f32) -> f32 {
+    if high - low <= half_size * 2.0 {
+        return (low + high) / 2.0;
+    }
+
+    return value.max(low + half_size).min(high - half_size);
+}
+
+fn clamp_length(vector: (f32, f32), max_length: f32) -> (f32, f32) {
+    let length = (vector.0 * vector.0 + vector.1 * vector.1).sqrt();
+    if length <= max_length || length == 0.0 {
+        return vector;
+    }
+
+    let scale = max_length / length;
+    return (vector.0 * scale, vector.1 * scale);
+}
+
+// Smooth noise from -1 to 1 made of sines at unrelated frequencies. The same time gives
+// the same shake, so it doesn't touch the random streams replays depend on.
+fn noise(time: f32, channel: f32) -> f32 {
+    let phase = channel * 12.9898;
+    return ((time + phase).sin() * 0.5
+        + (time * 2.173 + phase * 1.7).sin() * 0.3
+        + (time * 4.519 + phase * 2.3).sin() * 0.2)
+        .max(-1.0)
+        .min(1.0);
+}
@@ -4,11 +4,35 @@ pub struct GenerationalArray<T> {
     generation: u64,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Key {
     index: usize,
     generation: u64,
 }
 
+/// Which slots exist and their generations, without the values. Restoring a layout keeps
+/// keys handed out at the time valid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    generations: Vec<u64>,
+    free_list: Vec<usize>,
+    generation: u64,
+}
+
+impl Layout {
+    pub fn new() -> Layout {
+        return Layout {
+            generations: Vec::new(),
+            free_list: Vec::new(),
+            generation: 0,
+        };
+    }
+
+    pub fn len(&self) -> usize {
+        return self.generations.len();
+    }
+}
+
 struct Container<T> {
     generation: u64,
     value: Option<Box<T>>,
@@ -50,8 +74,11 @@ impl<T> GenerationalArray<T> {
     }
 
     pub fn get(&mut self, key: &Key) -> Option<&mut T> {
-        let container = &mut self.entities[key.index];
-        if container.generation > key.generation {
+        let container = match self.entities.get_mut(key.index) {
+            Some(container) => container,
+            None => return None,
+        };
+        if container.generation != key.generation {
             return None;
         }
 
@@ -61,6 +88,13 @@ impl<T> GenerationalArray<T> {
         };
     }
 
+    pub fn contains(&self, key: &Key) -> bool {
+        return match self.entities.get(key.index) {
+            Some(container) => container.generation == key.generation && container.value.is_some(),
+            None => false,
+        };
+    }
+
     pub fn get_raw(&mut self, index: usize) -> Option<&mut T> {
         let container = &mut self.entities[index];
         return match container.value {
@@ -69,13 +103,59 @@ impl<T> GenerationalArray<T> {
         };
     }
 
+    pub fn get_raw_ref(&self, index: usize) -> Option<&T> {
+        return match self.entities[index].value {
+            Some(ref value) => Some(value.as_ref()),
+            None => None,
+        };
+    }
+
     pub fn raw_len(&self) -> usize {
         return self.entities.len();
     }
 
+    /// Replaces the value in a slot without touching its generation or the free list
+    pub fn set_raw(&mut self, index: usize, value: Option<Box<T>>) {
+        self.entities[index].value = value;
+    }
+
+    pub fn layout(&self) -> Layout {
+        return Layout {
+            generations: self
+                .entities
+                .iter()
+                .map(|container| container.generation)
+                .collect(),
+            free_list: self.free_list.clone(),
+            generation: self.generation,
+        };
+    }
+
+    /// Resizes the array to the layout. Values in slots that are kept stay where they are,
+    /// slots that are added start out empty. Keys to slots the layout doesn't have, or to a
+    /// generation it doesn't know yet, stop finding anything.
+    pub fn restore_layout(&mut self, layout: &Layout) {
+        self.entities.truncate(layout.generations.len());
+        while self.entities.len() < layout.generations.len() {
+            self.entities.push(Container {
+                generation: 0,
+                value: None,
+            });
+        }
+
+        for (container, generation) in self.entities.iter_mut().zip(&layout.generations) {
+            container.generation = *generation;
+        }
+        self.free_list = layout.free_list.clone();
+        self.generation = layout.generation;
+    }
+
     pub fn remove(&mut self, key: &Key) {
-        let container = &mut self.entities[key.index];
-        if container.generation > key.generation {
+        let container = match self.entities.get_mut(key.index) {
+            Some(container) => container,
+            None => return,
+        };
+        if container.generation != key.generation || container.value.is_none() {
             return;
         }
 
@@ -84,3 +164,39 @@ impl<T> GenerationalArray<T> {
         self.generation += 1;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_from_after_a_restored_layout_find_nothing() {
+        let mut array = GenerationalArray::new();
+        let first = array.add(Box::new(1));
+        let layout = array.layout();
+        let second = array.add(Box::new(2));
+
+        array.restore_layout(&layout);
+        assert_eq!(array.get(&first).map(|value| *value), Some(1));
+        assert!(array.get(&second).is_none());
+        assert!(!array.contains(&second));
+        array.remove(&second);
+        assert_eq!(array.raw_len(), 1);
+    }
+
+    #[test]
+    fn newer_keys_do_not_find_older_values() {
+        let mut array = GenerationalArray::new();
+        let first = array.add(Box::new(1));
+        let layout = array.layout();
+        array.remove(&first);
+        let second = array.add(Box::new(2));
+        assert_eq!(second.index, first.index);
+
+        // Back to the first value, which the second key must not see
+        array.restore_layout(&layout);
+        array.set_raw(first.index, Some(Box::new(1)));
+        assert!(array.get(&second).is_none());
+        assert_eq!(array.get(&first).map(|value| *value), Some(1));
+    }
+}
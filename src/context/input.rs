@@ -0,0 +1,159 @@
+extern crate glutin;
+
+use std::collections::HashSet;
+
+/// A key, identified by its glutin `VirtualKeyCode`. Kept as a plain number so input can be
+/// written to and read back from replay files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyCode(pub u32);
+
+impl From<glutin::VirtualKeyCode> for KeyCode {
+    fn from(key: glutin::VirtualKeyCode) -> KeyCode {
+        return KeyCode(key as u32);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Other(u8),
+}
+
+impl From<glutin::MouseButton> for MouseButton {
+    fn from(button: glutin::MouseButton) -> MouseButton {
+        return match button {
+            glutin::MouseButton::Left => MouseButton::Left,
+            glutin::MouseButton::Right => MouseButton::Right,
+            glutin::MouseButton::Middle => MouseButton::Middle,
+            glutin::MouseButton::Other(index) => MouseButton::Other(index),
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+    KeyPressed(KeyCode),
+    KeyReleased(KeyCode),
+    /// Cursor position in physical pixels from the top left of the window
+    MouseMoved {
+        x: f32,
+        y: f32,
+    },
+    MousePressed(MouseButton),
+    MouseReleased(MouseButton),
+    MouseWheel {
+        dx: f32,
+        dy: f32,
+    },
+}
+
+/// Input state as of the current frame. The game runner feeds it every event that arrived
+/// since the previous frame before the game loop runs.
+pub struct Input {
+    events: Vec<InputEvent>,
+    keys_down: HashSet<KeyCode>,
+    keys_pressed: HashSet<KeyCode>,
+    keys_released: HashSet<KeyCode>,
+    buttons_down: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    mouse_position: (f32, f32),
+    mouse_wheel: (f32, f32),
+}
+
+impl Input {
+    pub fn new() -> Input {
+        return Input {
+            events: Vec::new(),
+            keys_down: HashSet::new(),
+            keys_pressed: HashSet::new(),
+            keys_released: HashSet::new(),
+            buttons_down: HashSet::new(),
+            buttons_pressed: HashSet::new(),
+            buttons_released: HashSet::new(),
+            mouse_position: (0.0, 0.0),
+            mouse_wheel: (0.0, 0.0),
+        };
+    }
+
+    pub fn begin_frame(&mut self, events: &[InputEvent]) {
+        self.events.clear();
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.mouse_wheel = (0.0, 0.0);
+
+        for event in events {
+            self.apply(event);
+        }
+    }
+
+    fn apply(&mut self, event: &InputEvent) {
+        self.events.push(*event);
+        match *event {
+            InputEvent::KeyPressed(key) => {
+                // Held keys repeat, but only the first press counts as pressed
+                if self.keys_down.insert(key) {
+                    self.keys_pressed.insert(key);
+                }
+            }
+            InputEvent::KeyReleased(key) => {
+                self.keys_down.remove(&key);
+                self.keys_released.insert(key);
+            }
+            InputEvent::MouseMoved { x, y } => self.mouse_position = (x, y),
+            InputEvent::MousePressed(button) => {
+                if self.buttons_down.insert(button) {
+                    self.buttons_pressed.insert(button);
+                }
+            }
+            InputEvent::MouseReleased(button) => {
+                self.buttons_down.remove(&button);
+                self.buttons_released.insert(button);
+            }
+            InputEvent::MouseWheel { dx, dy } => {
+                self.mouse_wheel = (self.mouse_wheel.0 + dx, self.mouse_wheel.1 + dy);
+            }
+        }
+    }
+
+    /// Everything that happened since the previous frame, in order
+    pub fn events(&self) -> &[InputEvent] {
+        return &self.events;
+    }
+
+    pub fn is_key_down<K: Into<KeyCode>>(&self, key: K) -> bool {
+        return self.keys_down.contains(&key.into());
+    }
+
+    pub fn was_key_pressed<K: Into<KeyCode>>(&self, key: K) -> bool {
+        return self.keys_pressed.contains(&key.into());
+    }
+
+    pub fn was_key_released<K: Into<KeyCode>>(&self, key: K) -> bool {
+        return self.keys_released.contains(&key.into());
+    }
+
+    pub fn is_mouse_down<B: Into<MouseButton>>(&self, button: B) -> bool {
+        return self.buttons_down.contains(&button.into());
+    }
+
+    pub fn was_mouse_pressed<B: Into<MouseButton>>(&self, button: B) -> bool {
+        return self.buttons_pressed.contains(&button.into());
+    }
+
+    pub fn was_mouse_released<B: Into<MouseButton>>(&self, button: B) -> bool {
+        return self.buttons_released.contains(&button.into());
+    }
+
+    pub fn mouse_position(&self) -> (f32, f32) {
+        return self.mouse_position;
+    }
+
+    pub fn mouse_wheel(&self) -> (f32, f32) {
+        return self.mouse_wheel;
+    }
+}
@@ -1,24 +1,126 @@
+//! The state a game works with every frame: entities, time, input, randomness, the camera
+//! and shared resources.
+
+pub mod animation;
 pub mod camera;
 pub mod clock;
+pub mod events;
+pub mod follow;
 pub mod generational;
+pub mod input;
+pub mod profiler;
+pub mod random;
+pub mod resources;
 pub mod screen;
+pub mod tasks;
+pub mod timers;
+pub mod tween;
 
+use self::clock::{Clock, TimeDomain};
+use self::events::Events;
+use self::generational::Key;
 use entities::Entity;
+use std::any::Any;
 
+/// Everything the game loop and systems share
 pub struct Context {
+    pub animations: animation::Animations,
+    /// The camera used when no entity has a `CameraView`
     pub camera: camera::Camera,
-    pub clock: clock::Clock,
+    /// Game time, see `TimeDomain::Game`
+    pub clock: Clock,
+    pub ui_clock: Clock,
+    pub real_clock: Clock,
     pub entities: generational::GenerationalArray<Entity>,
+    pub input: input::Input,
+    pub profiler: profiler::Profiler,
+    pub random: random::Random,
+    pub resources: resources::Resources,
     pub screen: screen::Screen,
+    pub tasks: tasks::Tasks,
+    pub timers: timers::Timers,
+    pub tweens: tween::Tweens,
+
+    exit_requested: bool,
+    entity_scope: Option<Vec<Key>>,
 }
 
 impl Context {
     pub fn new() -> Context {
         return Context {
+            animations: animation::Animations::new(),
             camera: camera::Camera::new(),
-            clock: clock::Clock::new(),
+            clock: Clock::new(),
+            ui_clock: Clock::new(),
+            real_clock: Clock::real(),
             entities: generational::GenerationalArray::new(),
+            input: input::Input::new(),
+            profiler: profiler::Profiler::new(),
+            random: random::Random::from_time(),
+            resources: resources::Resources::new(),
             screen: screen::Screen::new(),
+            tasks: tasks::Tasks::new(),
+            timers: timers::Timers::new(),
+            tweens: tween::Tweens::new(),
+
+            exit_requested: false,
+            entity_scope: None,
+        };
+    }
+
+    pub fn clock_for(&self, domain: TimeDomain) -> &Clock {
+        return match domain {
+            TimeDomain::Real => &self.real_clock,
+            TimeDomain::Ui => &self.ui_clock,
+            TimeDomain::Game => &self.clock,
         };
     }
+
+    pub fn clock_for_mut(&mut self, domain: TimeDomain) -> &mut Clock {
+        return match domain {
+            TimeDomain::Real => &mut self.real_clock,
+            TimeDomain::Ui => &mut self.ui_clock,
+            TimeDomain::Game => &mut self.clock,
+        };
+    }
+
+    /// Sends an event to everything reading events of its type. Events of types no plugin
+    /// added are dropped.
+    pub fn send_event<T: Any>(&mut self, event: T) {
+        if let Some(events) = self.resources.get_mut::<Events<T>>() {
+            events.send(event);
+        }
+    }
+
+    pub fn events<T: Any>(&self) -> Option<&Events<T>> {
+        return self.resources.get::<Events<T>>();
+    }
+
+    /// Adds an entity, recording it in the current entity scope if one is open
+    pub fn spawn(&mut self, entity: Box<Entity>) -> Key {
+        let key = self.entities.add(entity);
+        if let Some(ref mut scope) = self.entity_scope {
+            scope.push(key);
+        }
+
+        return key;
+    }
+
+    /// Entity scopes let the state stack find out which entities a state spawned
+    pub fn begin_entity_scope(&mut self) {
+        self.entity_scope = Some(Vec::new());
+    }
+
+    pub fn end_entity_scope(&mut self) -> Vec<Key> {
+        return self.entity_scope.take().unwrap_or_else(Vec::new);
+    }
+
+    /// Asks the game runner to stop after the current frame
+    pub fn request_exit(&mut self) {
+        self.exit_requested = true;
+    }
+
+    pub fn is_exit_requested(&self) -> bool {
+        return self.exit_requested;
+    }
 }
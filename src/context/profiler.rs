@@ -0,0 +1,119 @@
+use std::ops::Add;
+use std::time::Duration;
+
+const DEFAULT_HISTORY_LENGTH: usize = 240;
+
+/// Timing summary of a stage over the recorded history
+#[derive(Debug, Clone, Copy)]
+pub struct Stats {
+    pub samples: usize,
+    pub min: Duration,
+    pub avg: Duration,
+    pub max: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+struct Stage {
+    name: &'static str,
+    samples: Vec<Duration>,
+    next: usize,
+}
+
+/// Records how long each stage of the frame took over the last few seconds of frames
+pub struct Profiler {
+    enabled: bool,
+    history_length: usize,
+    stages: Vec<Stage>,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        return Profiler {
+            enabled: true,
+            history_length: DEFAULT_HISTORY_LENGTH,
+            stages: Vec::new(),
+        };
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        return self.enabled;
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// How many samples are kept per stage. Changing it clears what was recorded so far.
+    pub fn set_history_length(&mut self, history_length: usize) {
+        self.history_length = history_length.max(1);
+        self.stages.clear();
+    }
+
+    pub fn record(&mut self, name: &'static str, duration: Duration) {
+        if !self.enabled {
+            return;
+        }
+
+        let index = match self.stages.iter().position(|stage| stage.name == name) {
+            Some(index) => index,
+            None => {
+                self.stages.push(Stage {
+                    name: name,
+                    // The history can be long, so let it grow with the samples
+                    samples: Vec::new(),
+                    next: 0,
+                });
+                self.stages.len() - 1
+            }
+        };
+
+        // The samples form a ring buffer once it is full
+        let stage = &mut self.stages[index];
+        if stage.samples.len() < self.history_length {
+            stage.samples.push(duration);
+        } else {
+            stage.samples[stage.next] = duration;
+        }
+        stage.next = (stage.next + 1) % self.history_length;
+    }
+
+    /// Stage names in the order they were first recorded
+    pub fn stages(&self) -> Vec<&'static str> {
+        return self.stages.iter().map(|stage| stage.name).collect();
+    }
+
+    pub fn stats(&self, name: &str) -> Option<Stats> {
+        let stage = match self.stages.iter().find(|stage| stage.name == name) {
+            Some(stage) => stage,
+            None => return None,
+        };
+
+        if stage.samples.is_empty() {
+            return None;
+        }
+
+        let mut sorted = stage.samples.clone();
+        sorted.sort();
+
+        let total = sorted
+            .iter()
+            .fold(Duration::from_millis(0), |total, sample| total.add(*sample));
+        let percentile = |p: f64| sorted[((sorted.len() - 1) as f64 * p).round() as usize];
+
+        return Some(Stats {
+            samples: sorted.len(),
+            min: sorted[0],
+            avg: total / sorted.len() as u32,
+            max: sorted[sorted.len() - 1],
+            p50: percentile(0.5),
+            p95: percentile(0.95),
+            p99: percentile(0.99),
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.stages.clear();
+    }
+}
@@ -0,0 +1,123 @@
+extern crate rand;
+
+use rand::{Error, RngCore};
+use std::collections::BTreeMap;
+use std::hash::Hasher;
+use std::time::{SystemTime, UNIX_EPOCH};
+use util::hash::Fnv;
+
+/// A small, fast generator (SplitMix64) whose whole state is a single u64, which makes it
+/// cheap to save, restore and write to replay files.
+#[derive(Debug, Clone)]
+pub struct Stream {
+    state: u64,
+}
+
+impl RngCore for Stream {
+    fn next_u32(&mut self) -> u32 {
+        return (self.next_u64() >> 32) as u32;
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        return mix(self.state);
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            let length = chunk.len();
+            chunk.copy_from_slice(&bytes[..length]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        return Ok(());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RandomState {
+    pub seed: u64,
+    pub streams: Vec<(String, u64)>,
+}
+
+/// The engine's source of randomness. Each named stream is seeded from the master seed and
+/// its name alone, so drawing from one stream never changes the numbers another one gives.
+pub struct Random {
+    seed: u64,
+    streams: BTreeMap<String, Stream>,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        return Random {
+            seed: seed,
+            streams: BTreeMap::new(),
+        };
+    }
+
+    /// Seeds from the system time, for when a run doesn't need to be reproducible
+    pub fn from_time() -> Random {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() ^ ((duration.subsec_nanos() as u64) << 32))
+            .unwrap_or(0);
+        return Random::new(mix(now));
+    }
+
+    pub fn seed(&self) -> u64 {
+        return self.seed;
+    }
+
+    /// Starts over from a new seed, resetting every stream
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.streams.clear();
+    }
+
+    pub fn stream(&mut self, name: &str) -> &mut Stream {
+        if !self.streams.contains_key(name) {
+            let stream = Stream {
+                state: mix(self.seed ^ hash_name(name)),
+            };
+            self.streams.insert(name.to_string(), stream);
+        }
+
+        return self.streams.get_mut(name).unwrap();
+    }
+
+    pub fn save_state(&self) -> RandomState {
+        return RandomState {
+            seed: self.seed,
+            streams: self
+                .streams
+                .iter()
+                .map(|(name, stream)| (name.clone(), stream.state))
+                .collect(),
+        };
+    }
+
+    pub fn restore_state(&mut self, state: &RandomState) {
+        self.seed = state.seed;
+        self.streams = state
+            .streams
+            .iter()
+            .map(|&(ref name, state)| (name.clone(), Stream { state: state }))
+            .collect();
+    }
+}
+
+fn mix(value: u64) -> u64 {
+    let mut z = value;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    return z ^ (z >> 31);
+}
+
+fn hash_name(name: &str) -> u64 {
+    let mut hasher = Fnv::new();
+    hasher.write(name.as_bytes());
+    return hasher.finish();
+}
@@ -0,0 +1,47 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+/// Values of any type, at most one per type, that plugins and games share through the
+/// context
+pub struct Resources {
+    values: HashMap<TypeId, Box<Any>>,
+}
+
+impl Resources {
+    pub fn new() -> Resources {
+        return Resources {
+            values: HashMap::new(),
+        };
+    }
+
+    /// Stores the value, replacing any earlier value of the same type
+    pub fn insert<T: Any>(&mut self, value: T) {
+        self.values.insert(TypeId::of::<T>(), Box::new(value));
+    }
+
+    pub fn remove<T: Any>(&mut self) -> Option<T> {
+        return self
+            .values
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok())
+            .map(|value: Box<T>| *value);
+    }
+
+    pub fn contains<T: Any>(&self) -> bool {
+        return self.values.contains_key(&TypeId::of::<T>());
+    }
+
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        return self
+            .values
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref());
+    }
+
+    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        return self
+            .values
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_mut());
+    }
+}
@@ -1,6 +1,8 @@
+/// The window's size in physical pixels and how many physical pixels make up a logical one
 pub struct Screen {
     pub width: u32,
     pub height: u32,
+    pub dpi_factor: f32,
 }
 
 impl Screen {
@@ -8,6 +10,7 @@ impl Screen {
         return Screen {
             width: 0,
             height: 0,
+            dpi_factor: 1.0,
         };
     }
 
@@ -15,4 +18,22 @@ impl Screen {
         self.width = width;
         self.height = height;
     }
+
+    /// Called by the game runner when the window moves to a monitor with a different DPI
+    pub fn set_dpi_factor(&mut self, dpi_factor: f32) {
+        self.dpi_factor = dpi_factor;
+    }
+
+    pub fn to_physical(&self, x: f32, y: f32) -> (f32, f32) {
+        return (x * self.dpi_factor, y * self.dpi_factor);
+    }
+
+    pub fn to_logical(&self, x: f32, y: f32) -> (f32, f32) {
+        return (x / self.dpi_factor, y / self.dpi_factor);
+    }
+
+    /// The window's size in logical pixels
+    pub fn logical_size(&self) -> (f32, f32) {
+        return self.to_logical(self.width as f32, self.height as f32);
+    }
 }
@@ -0,0 +1,425 @@
+use context::clock::TimeDomain;
+use context::events::EventReader;
+use context::generational::Key;
+use context::tween::TweenHandle;
+use context::Context;
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Checks whether the event a task waits for has been sent
+pub type EventCheck = Box<FnMut(&Context) -> bool>;
+
+/// What a task is waiting for before it's resumed again
+pub enum Wait {
+    /// This many frames, one being the next frame
+    Frames(u64),
+    /// Time passing on the task's clock
+    Time(Duration),
+    Event(EventCheck),
+    Tween(TweenHandle),
+}
+
+/// What a task returns each time it's resumed
+pub enum Step {
+    Wait(Wait),
+    Done,
+}
+
+/// Resumes on the next frame
+pub fn next_frame() -> Step {
+    return wait_frames(1);
+}
+
+pub fn wait_frames(frames: u64) -> Step {
+    return Step::Wait(Wait::Frames(frames.max(1)));
+}
+
+pub fn wait_seconds(seconds: f32) -> Step {
+    let nanos = (seconds.max(0.0) as f64 * 1e9) as u64;
+    return Step::Wait(Wait::Time(Duration::new(
+        nanos / 1_000_000_000,
+        (nanos % 1_000_000_000) as u32,
+    )));
+}
+
+/// Resumes once an event of the type is sent after the task started waiting
+pub fn wait_event<T: Any>() -> Step {
+    return wait_event_where::<T, _>(|_| true);
+}
+
+/// Resumes once an event of the type matching the filter is sent
+pub fn wait_event_where<T: Any, F: Fn(&T) -> bool + 'static>(filter: F) -> Step {
+    let mut reader: Option<EventReader> = None;
+    return Step::Wait(Wait::Event(Box::new(move |context: &Context| {
+        let events = match context.events::<T>() {
+            Some(events) => events,
+            None => return false,
+        };
+
+        // Start reading from when the task was resumed, not from what's still buffered
+        let reader = reader.get_or_insert_with(|| events.reader_at_end());
+        return events.read(reader).into_iter().any(|event| filter(event));
+    })));
+}
+
+/// Resumes once the tween has finished or was cancelled
+pub fn wait_tween(handle: TweenHandle) -> Step {
+    return Step::Wait(Wait::Tween(handle));
+}
+
+pub fn done() -> Step {
+    return Step::Done;
+}
+
+/// A sequence of steps spread over frames, like "fade out, wait 2 seconds, spawn the boss".
+/// Tasks are state machines that are resumed with the context whenever what they wait for
+/// has happened. Closures taking the context and returning a `Step` are tasks, and
+/// `Script` strings simple sequences together.
+pub trait Task {
+    fn resume(&mut self, context: &mut Context) -> Step;
+}
+
+impl<F: FnMut(&mut Context) -> Step> Task for F {
+    fn resume(&mut self, context: &mut Context) -> Step {
+        return self(context);
+    }
+}
+
+/// A task that runs its steps in order, waiting for whatever each one returns
+pub struct Script {
+    steps: Vec<Box<FnMut(&mut Context) -> Step>>,
+    next: usize,
+}
+
+impl Script {
+    pub fn new() -> Script {
+        return Script {
+            steps: Vec::new(),
+            next: 0,
+        };
+    }
+
+    /// Runs the closure, then goes straight on to the next step
+    pub fn then<F: FnMut(&mut Context) + 'static>(mut self, mut step: F) -> Script {
+        self.steps.push(Box::new(move |context: &mut Context| {
+            step(context);
+            return Step::Done;
+        }));
+        return self;
+    }
+
+    /// Runs the closure and waits for what it returns, e.g. a tween it started
+    pub fn then_wait<F: FnMut(&mut Context) -> Step + 'static>(mut self, step: F) -> Script {
+        self.steps.push(Box::new(step));
+        return self;
+    }
+
+    pub fn wait_seconds(self, seconds: f32) -> Script {
+        return self.then_wait(move |_| wait_seconds(seconds));
+    }
+
+    pub fn wait_frames(self, frames: u64) -> Script {
+        return self.then_wait(move |_| wait_frames(frames));
+    }
+
+    pub fn wait_event<T: Any>(self) -> Script {
+        return self.then_wait(|_| wait_event::<T>());
+    }
+}
+
+impl Task for Script {
+    fn resume(&mut self, context: &mut Context) -> Step {
+        while self.next < self.steps.len() {
+            let step = (self.steps[self.next])(context);
+            self.next += 1;
+            if let Step::Wait(wait) = step {
+                return Step::Wait(wait);
+            }
+        }
+
+        return Step::Done;
+    }
+}
+
+/// Identifies a spawned task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TaskHandle(u64);
+
+/// A group of tasks that are cancelled together, e.g. the ones a state started
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TaskScope(u64);
+
+struct Running {
+    task: Box<Task>,
+    // Nothing to wait for before the first resume
+    wait: Option<Wait>,
+    owner: Option<Key>,
+    domain: TimeDomain,
+    scope: Option<TaskScope>,
+}
+
+/// Runs tasks once per frame, resuming the ones whose wait is over. Tasks join the scope
+/// that's current when they're spawned. The state stack makes each state's scope current
+/// while the state runs, and tasks spawned by a task join that task's scope, so
+/// everything a state started is cancelled when it exits.
+pub struct Tasks {
+    next_handle: u64,
+    next_scope: u64,
+    running: BTreeMap<TaskHandle, Running>,
+    scope: Option<TaskScope>,
+    // The task being resumed, which is taken out of `running` meanwhile, and whether it
+    // was cancelled since
+    resuming: Option<(TaskHandle, Option<TaskScope>, bool)>,
+}
+
+impl Tasks {
+    pub fn new() -> Tasks {
+        return Tasks {
+            next_handle: 0,
+            next_scope: 0,
+            running: BTreeMap::new(),
+            scope: None,
+            resuming: None,
+        };
+    }
+
+    /// Starts a task driven by game time. It's first resumed in the next task update.
+    pub fn spawn<T: Task + 'static>(&mut self, task: T) -> TaskHandle {
+        return self.start(Box::new(task), None, TimeDomain::Game);
+    }
+
+    /// Starts a task that's cancelled when the entity is removed
+    pub fn spawn_owned<T: Task + 'static>(&mut self, owner: Key, task: T) -> TaskHandle {
+        return self.start(Box::new(task), Some(owner), TimeDomain::Game);
+    }
+
+    /// Starts a task whose `wait_seconds` follow another clock, e.g. UI time so it keeps
+    /// going while the game is paused
+    pub fn spawn_in_domain<T: Task + 'static>(
+        &mut self,
+        domain: TimeDomain,
+        task: T,
+    ) -> TaskHandle {
+        return self.start(Box::new(task), None, domain);
+    }
+
+    fn start(&mut self, task: Box<Task>, owner: Option<Key>, domain: TimeDomain) -> TaskHandle {
+        let handle = TaskHandle(self.next_handle);
+        self.next_handle += 1;
+
+        let scope = self.scope;
+        self.running.insert(
+            handle,
+            Running {
+                task: task,
+                wait: None,
+                owner: owner,
+                domain: domain,
+                scope: scope,
+            },
+        );
+        return handle;
+    }
+
+    pub fn new_scope(&mut self) -> TaskScope {
+        let scope = TaskScope(self.next_scope);
+        self.next_scope += 1;
+        return scope;
+    }
+
+    /// The scope tasks spawned from now on join
+    pub fn scope(&self) -> Option<TaskScope> {
+        return self.scope;
+    }
+
+    pub fn set_scope(&mut self, scope: Option<TaskScope>) {
+        self.scope = scope;
+    }
+
+    pub fn cancel(&mut self, handle: TaskHandle) {
+        self.running.remove(&handle);
+        if let Some((resuming, _, ref mut cancelled)) = self.resuming {
+            if resuming == handle {
+                *cancelled = true;
+            }
+        }
+    }
+
+    /// Cancels every task in the scope
+    pub fn cancel_scope(&mut self, scope: TaskScope) {
+        self.running
+            .retain(|_, running| running.scope != Some(scope));
+        if let Some((_, resuming, ref mut cancelled)) = self.resuming {
+            if resuming == Some(scope) {
+                *cancelled = true;
+            }
+        }
+    }
+
+    pub fn is_running(&self, handle: TaskHandle) -> bool {
+        let resuming = match self.resuming {
+            Some((resuming, _, false)) => resuming == handle,
+            _ => false,
+        };
+        return self.running.contains_key(&handle) || resuming;
+    }
+
+    pub fn len(&self) -> usize {
+        let resuming = match self.resuming {
+            Some((_, _, false)) => 1,
+            _ => 0,
+        };
+        return self.running.len() + resuming;
+    }
+
+    /// Cancels every task
+    pub fn clear(&mut self) {
+        self.running.clear();
+        if let Some((_, _, ref mut cancelled)) = self.resuming {
+            *cancelled = true;
+        }
+    }
+}
+
+/// Resumes every task whose wait is over, dropping tasks that are done or whose owner is
+/// gone
+pub fn update(context: &mut Context) {
+    // Tasks spawned during the update wait for the next one
+    let handles: Vec<TaskHandle> = context.tasks.running.keys().cloned().collect();
+    for handle in handles {
+        // Each task is taken out while it runs so it can change the rest of the context.
+        // Tasks cancelled by the ones before it are already gone.
+        let mut running = match context.tasks.running.remove(&handle) {
+            Some(running) => running,
+            None => continue,
+        };
+
+        if let Some(owner) = running.owner {
+            if !context.entities.contains(&owner) {
+                continue;
+            }
+        }
+
+        let ready = match running.wait {
+            None => true,
+            Some(Wait::Frames(ref mut frames)) => {
+                *frames = frames.saturating_sub(1);
+                *frames == 0
+            }
+            Some(Wait::Time(ref mut remaining)) => {
+                let delta = context.clock_for(running.domain).delta();
+                *remaining = remaining
+                    .checked_sub(delta)
+                    .unwrap_or(Duration::from_millis(0));
+                *remaining == Duration::from_millis(0)
+            }
+            Some(Wait::Event(ref mut check)) => check(context),
+            Some(Wait::Tween(tween)) => !context.tweens.is_running(tween),
+        };
+        if !ready {
+            context.tasks.running.insert(handle, running);
+            continue;
+        }
+
+        // Tasks the task spawns join its scope
+        let outer_scope = context.tasks.scope;
+        context.tasks.scope = running.scope;
+        context.tasks.resuming = Some((handle, running.scope, false));
+        let step = running.task.resume(context);
+        let cancelled = match context.tasks.resuming.take() {
+            Some((_, _, cancelled)) => cancelled,
+            None => false,
+        };
+        context.tasks.scope = outer_scope;
+
+        if cancelled {
+            continue;
+        }
+
+        match step {
+            Step::Wait(Wait::Event(mut check)) => {
+                // The first check starts reading events, so ones sent later this frame count
+                check(context);
+                running.wait = Some(Wait::Event(check));
+            }
+            Step::Wait(wait) => running.wait = Some(wait),
+            Step::Done => continue,
+        }
+        context.tasks.running.insert(handle, running);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn zero_frame_wait_resumes_next_frame() {
+        let mut context = Context::new();
+        let resumed = Rc::new(Cell::new(0));
+        let counter = resumed.clone();
+        context.tasks.spawn(move |_: &mut Context| {
+            counter.set(counter.get() + 1);
+            return Step::Wait(Wait::Frames(0));
+        });
+
+        for _ in 0..3 {
+            update(&mut context);
+        }
+        assert_eq!(resumed.get(), 3);
+    }
+
+    #[test]
+    fn tasks_are_running_while_others_resume() {
+        let mut context = Context::new();
+        let other = context.tasks.spawn(|_: &mut Context| next_frame());
+        let seen = Rc::new(Cell::new((false, 0)));
+        let result = seen.clone();
+        context.tasks.spawn(move |context: &mut Context| {
+            result.set((context.tasks.is_running(other), context.tasks.len()));
+            return next_frame();
+        });
+
+        update(&mut context);
+        assert_eq!(seen.get(), (true, 2));
+    }
+
+    #[test]
+    fn clear_cancels_every_task_during_update() {
+        let mut context = Context::new();
+        context.tasks.spawn(|_: &mut Context| next_frame());
+        context.tasks.spawn(|context: &mut Context| {
+            context.tasks.clear();
+            return next_frame();
+        });
+        context.tasks.spawn(|_: &mut Context| next_frame());
+
+        update(&mut context);
+        assert_eq!(context.tasks.len(), 0);
+    }
+
+    #[test]
+    fn spawned_tasks_join_the_spawning_task_scope() {
+        let mut context = Context::new();
+        let scope = context.tasks.new_scope();
+        let child = Rc::new(Cell::new(None));
+        let spawned = child.clone();
+
+        context.tasks.set_scope(Some(scope));
+        context.tasks.spawn(move |context: &mut Context| {
+            spawned.set(Some(context.tasks.spawn(|_: &mut Context| next_frame())));
+            return done();
+        });
+        context.tasks.set_scope(None);
+
+        update(&mut context);
+        let child = child.get().unwrap();
+        assert!(context.tasks.is_running(child));
+
+        context.tasks.cancel_scope(scope);
+        assert!(!context.tasks.is_running(child));
+    }
+}
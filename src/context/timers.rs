@@ -0,0 +1,302 @@
+use context::clock::{Clock, TimeDomain};
+use context::generational::{GenerationalArray, Key};
+use context::Context;
+use entities::Entity;
+use std::collections::BTreeMap;
+use std::ops::Sub;
+use std::time::Duration;
+
+pub type TimerCallback = Box<FnMut(&mut Context, TimerHandle)>;
+
+/// Identifies a timer so it can be cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TimerHandle(u64);
+
+/// Sent every time a timer goes off
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimerFired {
+    pub handle: TimerHandle,
+    pub entity: Option<Key>,
+}
+
+/// A one-shot or repeating timer, to be started with `Timers::start`
+pub struct Timer {
+    delay: Duration,
+    interval: Option<Duration>,
+    domain: TimeDomain,
+    entity: Option<Key>,
+    callback: Option<TimerCallback>,
+}
+
+impl Timer {
+    /// Goes off once after the delay
+    pub fn once(delay: Duration) -> Timer {
+        return Timer {
+            delay: delay,
+            interval: None,
+            domain: TimeDomain::Game,
+            entity: None,
+            callback: None,
+        };
+    }
+
+    /// Goes off every interval until cancelled
+    pub fn repeating(interval: Duration) -> Timer {
+        let mut timer = Timer::once(interval);
+        timer.interval = Some(interval);
+        return timer;
+    }
+
+    /// Which clock the timer follows, game time by default
+    pub fn in_domain(mut self, domain: TimeDomain) -> Timer {
+        self.domain = domain;
+        return self;
+    }
+
+    /// Drops the timer once the entity is removed
+    pub fn for_entity(mut self, entity: Key) -> Timer {
+        self.entity = Some(entity);
+        return self;
+    }
+
+    /// Called every time the timer goes off, in addition to the `TimerFired` event
+    pub fn with_callback<F: FnMut(&mut Context, TimerHandle) + 'static>(
+        mut self,
+        callback: F,
+    ) -> Timer {
+        self.callback = Some(Box::new(callback));
+        return self;
+    }
+}
+
+struct Running {
+    remaining: Duration,
+    // Finished timers are kept until the next advance so their callbacks can still run
+    done: bool,
+    timer: Timer,
+}
+
+/// The running timers. They are advanced by the timer system during the pre-update stage,
+/// after the clocks have ticked, so they fire before the game loop of the frame they are
+/// due in.
+pub struct Timers {
+    next_handle: u64,
+    running: BTreeMap<TimerHandle, Running>,
+}
+
+impl Timers {
+    pub fn new() -> Timers {
+        return Timers {
+            next_handle: 0,
+            running: BTreeMap::new(),
+        };
+    }
+
+    pub fn start(&mut self, timer: Timer) -> TimerHandle {
+        let handle = TimerHandle(self.next_handle);
+        self.next_handle += 1;
+        self.running.insert(
+            handle,
+            Running {
+                remaining: timer.delay,
+                done: false,
+                timer: timer,
+            },
+        );
+        return handle;
+    }
+
+    /// Stops the timer, returning whether it was still running
+    pub fn cancel(&mut self, handle: TimerHandle) -> bool {
+        return match self.running.remove(&handle) {
+            Some(running) => !running.done,
+            None => false,
+        };
+    }
+
+    pub fn is_running(&self, handle: TimerHandle) -> bool {
+        return self.running_timer(handle).is_some();
+    }
+
+    /// Time left until the timer next goes off
+    pub fn remaining(&self, handle: TimerHandle) -> Option<Duration> {
+        return self.running_timer(handle).map(|running| running.remaining);
+    }
+
+    pub fn len(&self) -> usize {
+        return self
+            .running
+            .values()
+            .filter(|running| !running.done)
+            .count();
+    }
+
+    pub fn clear(&mut self) {
+        self.running.clear();
+    }
+
+    /// Moves every timer forward by its clock's delta and drops timers whose entity is
+    /// gone. Returns the timers that went off, a repeating timer once for every interval
+    /// that passed.
+    pub fn advance(
+        &mut self,
+        clocks: [&Clock; 3],
+        entities: &GenerationalArray<Entity>,
+    ) -> Vec<TimerFired> {
+        let done: Vec<TimerHandle> = self
+            .running
+            .iter()
+            .filter(|&(_, running)| running.done)
+            .map(|(handle, _)| *handle)
+            .collect();
+        for handle in done {
+            self.running.remove(&handle);
+        }
+
+        let mut fired = Vec::new();
+        for (handle, running) in self.running.iter_mut() {
+            if let Some(ref entity) = running.timer.entity {
+                if !entities.contains(entity) {
+                    running.done = true;
+                    continue;
+                }
+            }
+
+            let mut delta = clocks[domain_index(running.timer.domain)].delta();
+            while !running.done && delta >= running.remaining {
+                delta = delta.sub(running.remaining);
+                fired.push(TimerFired {
+                    handle: *handle,
+                    entity: running.timer.entity,
+                });
+
+                match running.timer.interval {
+                    // Zero intervals would go off forever
+                    Some(interval) if interval > Duration::from_millis(0) => {
+                        running.remaining = interval;
+                    }
+                    _ => running.done = true,
+                }
+            }
+
+            if !running.done {
+                running.remaining = running.remaining.sub(delta);
+            }
+        }
+
+        return fired;
+    }
+
+    /// Lends out a timer's callback so it can be called with the context
+    pub fn take_callback(&mut self, handle: TimerHandle) -> Option<TimerCallback> {
+        return self
+            .running
+            .get_mut(&handle)
+            .and_then(|running| running.timer.callback.take());
+    }
+
+    /// Hands a callback back after it was called, unless the timer was cancelled since
+    pub fn return_callback(&mut self, handle: TimerHandle, callback: TimerCallback) {
+        if let Some(running) = self.running.get_mut(&handle) {
+            running.timer.callback = Some(callback);
+        }
+    }
+
+    fn running_timer(&self, handle: TimerHandle) -> Option<&Running> {
+        return self.running.get(&handle).filter(|running| !running.done);
+    }
+}
+
+fn domain_index(domain: TimeDomain) -> usize {
+    return match domain {
+        TimeDomain::Real => 0,
+        TimeDomain::Ui => 1,
+        TimeDomain::Game => 2,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A clock that has just ticked by the step
+    fn clock(step: Duration) -> Clock {
+        let mut clock = Clock::new();
+        clock.set_fixed_timestep(Some(step));
+        clock.tick();
+        return clock;
+    }
+
+    fn advance(timers: &mut Timers, clock: &Clock) -> Vec<TimerHandle> {
+        let entities = GenerationalArray::new();
+        return timers
+            .advance([clock, clock, clock], &entities)
+            .iter()
+            .map(|fired| fired.handle)
+            .collect();
+    }
+
+    #[test]
+    fn one_shot_timers_fire_once() {
+        let mut timers = Timers::new();
+        let handle = timers.start(Timer::once(Duration::from_millis(100)));
+        let clock = clock(Duration::from_millis(60));
+
+        assert!(advance(&mut timers, &clock).is_empty());
+        assert_eq!(advance(&mut timers, &clock), vec![handle]);
+        assert!(!timers.is_running(handle));
+        assert!(advance(&mut timers, &clock).is_empty());
+        assert_eq!(timers.len(), 0);
+    }
+
+    #[test]
+    fn repeating_timers_catch_up_on_long_frames() {
+        let mut timers = Timers::new();
+        let handle = timers.start(Timer::repeating(Duration::from_millis(100)));
+
+        let fired = advance(&mut timers, &clock(Duration::from_millis(350)));
+        assert_eq!(fired, vec![handle, handle, handle]);
+        assert_eq!(timers.remaining(handle), Some(Duration::from_millis(50)));
+        assert!(timers.is_running(handle));
+    }
+
+    #[test]
+    fn timers_follow_their_domain() {
+        let real = clock(Duration::from_millis(100));
+        let mut ui = Clock::new();
+        ui.set_fixed_timestep(Some(Duration::from_millis(100)));
+        ui.set_time_scale(0.5);
+        ui.tick();
+        let mut game = Clock::new();
+        game.set_fixed_timestep(Some(Duration::from_millis(100)));
+        game.pause();
+        game.tick();
+
+        let mut timers = Timers::new();
+        let delay = Duration::from_millis(200);
+        let on_real = timers.start(Timer::once(delay).in_domain(TimeDomain::Real));
+        let on_ui = timers.start(Timer::once(delay).in_domain(TimeDomain::Ui));
+        let on_game = timers.start(Timer::once(delay));
+
+        let entities = GenerationalArray::new();
+        let fired: Vec<TimerHandle> = timers
+            .advance([&real, &ui, &game], &entities)
+            .iter()
+            .map(|fired| fired.handle)
+            .collect();
+        assert!(fired.is_empty());
+        assert_eq!(timers.remaining(on_real), Some(Duration::from_millis(100)));
+        assert_eq!(timers.remaining(on_ui), Some(Duration::from_millis(150)));
+        assert_eq!(timers.remaining(on_game), Some(delay));
+    }
+
+    #[test]
+    fn cancelled_timers_stop_running() {
+        let mut timers = Timers::new();
+        let handle = timers.start(Timer::repeating(Duration::from_millis(10)));
+
+        assert!(timers.cancel(handle));
+        assert!(!timers.cancel(handle));
+        assert!(advance(&mut timers, &clock(Duration::from_millis(100))).is_empty());
+    }
+}
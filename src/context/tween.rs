@@ -0,0 +1,634 @@
+use components::renderable::Renderable;
+use context::clock::TimeDomain;
+use context::generational::Key;
+use context::Context;
+use entities::Entity;
+use std::collections::BTreeMap;
+use std::mem;
+use std::ops::{Add, Sub};
+use std::time::Duration;
+use util::easing::Easing;
+
+/// Gives a tween access to a numeric field of one of an entity's components, e.g.
+/// `|entity| entity.get_component::<Health>("health").map(|health| &mut health.value)`
+pub type FieldAccessor = fn(&mut Entity) -> Option<&mut f32>;
+
+/// What a tween animates. Each target has a fixed number of values: two for positions and
+/// sizes, three for colors and one for fields.
+#[derive(Clone, Copy)]
+pub enum TweenTarget {
+    Position(Key),
+    Size(Key),
+    Color(Key),
+    CameraPosition,
+    Field(Key, FieldAccessor),
+}
+
+/// Identifies a running tween
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TweenHandle(u64);
+
+/// Sent when a tween has played to the end or its target was removed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TweenFinished {
+    pub handle: TweenHandle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Repeat {
+    Times(u32),
+    Forever,
+}
+
+enum Kind {
+    Property {
+        target: TweenTarget,
+        to: Vec<f32>,
+        duration: Duration,
+        easing: Easing,
+        // Read from the target when the tween first starts moving
+        from: Option<Vec<f32>>,
+        elapsed: Duration,
+    },
+    Sequence {
+        tweens: Vec<Tween>,
+        current: usize,
+    },
+    Parallel {
+        tweens: Vec<Tween>,
+        // Time left over by each tween that has finished
+        leftovers: Vec<Option<Duration>>,
+    },
+}
+
+/// Moves values to a target over time, or plays other tweens one after another or all at
+/// once. Tweens are started with `Tweens::start` and follow game time by default, so they
+/// stop while the game is paused and speed up and slow down with its time scale.
+pub struct Tween {
+    kind: Kind,
+    delay: Duration,
+    delay_left: Duration,
+    repeat: Repeat,
+    iteration: u32,
+    yoyo: bool,
+    domain: TimeDomain,
+}
+
+impl Tween {
+    /// Animates the target from its current values to `to`
+    pub fn new(target: TweenTarget, to: &[f32], duration: Duration) -> Tween {
+        return Tween::with_kind(Kind::Property {
+            target: target,
+            to: to.to_vec(),
+            duration: duration,
+            easing: Easing::Linear,
+            from: None,
+            elapsed: Duration::from_millis(0),
+        });
+    }
+
+    pub fn move_to(entity: Key, x: f32, y: f32, duration: Duration) -> Tween {
+        return Tween::new(TweenTarget::Position(entity), &[x, y], duration);
+    }
+
+    pub fn resize_to(entity: Key, width: f32, height: f32, duration: Duration) -> Tween {
+        return Tween::new(TweenTarget::Size(entity), &[width, height], duration);
+    }
+
+    pub fn color_to(entity: Key, color: [f32; 3], duration: Duration) -> Tween {
+        return Tween::new(TweenTarget::Color(entity), &color, duration);
+    }
+
+    pub fn camera_to(x: f32, y: f32, duration: Duration) -> Tween {
+        return Tween::new(TweenTarget::CameraPosition, &[x, y], duration);
+    }
+
+    pub fn field(entity: Key, accessor: FieldAccessor, to: f32, duration: Duration) -> Tween {
+        return Tween::new(TweenTarget::Field(entity, accessor), &[to], duration);
+    }
+
+    /// Plays the tweens one after another
+    pub fn sequence(tweens: Vec<Tween>) -> Tween {
+        return Tween::with_kind(Kind::Sequence {
+            tweens: tweens,
+            current: 0,
+        });
+    }
+
+    /// Plays the tweens at the same time, finishing with the longest one
+    pub fn parallel(tweens: Vec<Tween>) -> Tween {
+        let leftovers = tweens.iter().map(|_| None).collect();
+        return Tween::with_kind(Kind::Parallel {
+            tweens: tweens,
+            leftovers: leftovers,
+        });
+    }
+
+    fn with_kind(kind: Kind) -> Tween {
+        return Tween {
+            kind: kind,
+            delay: Duration::from_millis(0),
+            delay_left: Duration::from_millis(0),
+            repeat: Repeat::Times(0),
+            iteration: 0,
+            yoyo: false,
+            domain: TimeDomain::Game,
+        };
+    }
+
+    /// The easing curve of a property tween, linear by default
+    pub fn easing(mut self, easing: Easing) -> Tween {
+        if let Kind::Property {
+            easing: ref mut current,
+            ..
+        } = self.kind
+        {
+            *current = easing;
+        }
+        return self;
+    }
+
+    /// Waits this long before starting. Repeats don't wait again.
+    pub fn delay(mut self, delay: Duration) -> Tween {
+        self.delay = delay;
+        self.delay_left = delay;
+        return self;
+    }
+
+    /// Plays this many more times after the first
+    pub fn repeat(mut self, times: u32) -> Tween {
+        self.repeat = Repeat::Times(times);
+        return self;
+    }
+
+    pub fn repeat_forever(mut self) -> Tween {
+        self.repeat = Repeat::Forever;
+        return self;
+    }
+
+    /// Plays every other repeat of a property tween backwards instead of starting over
+    pub fn yoyo(mut self) -> Tween {
+        self.yoyo = true;
+        return self;
+    }
+
+    /// Which clock drives the tween, game time by default. Only applies to tweens that are
+    /// started, not ones inside sequences and groups.
+    pub fn in_domain(mut self, domain: TimeDomain) -> Tween {
+        self.domain = domain;
+        return self;
+    }
+
+    // How long one play takes, without the delay and repeats
+    fn play_duration(&self) -> Duration {
+        return match self.kind {
+            Kind::Property { duration, .. } => duration,
+            Kind::Sequence { ref tweens, .. } => tweens
+                .iter()
+                .fold(Duration::from_millis(0), |total, tween| {
+                    total.add(tween.delay).add(tween.play_duration())
+                }),
+            Kind::Parallel { ref tweens, .. } => tweens
+                .iter()
+                .map(|tween| tween.delay.add(tween.play_duration()))
+                .max()
+                .unwrap_or(Duration::from_millis(0)),
+        };
+    }
+
+    // Moves the tween forward, returning the time left over once it has finished
+    fn advance(&mut self, delta: Duration, context: &mut Context) -> Option<Duration> {
+        let mut delta = delta;
+        if delta < self.delay_left {
+            self.delay_left = self.delay_left.sub(delta);
+            return None;
+        }
+        delta = delta.sub(self.delay_left);
+        self.delay_left = Duration::from_millis(0);
+
+        loop {
+            let reversed = self.yoyo && self.iteration % 2 == 1;
+            delta = match self.play(delta, reversed, context) {
+                Some(leftover) => leftover,
+                None => return None,
+            };
+
+            let repeats = match self.repeat {
+                Repeat::Times(times) => self.iteration < times,
+                Repeat::Forever => true,
+            };
+            // Tweens that take no time would repeat forever within one frame
+            if !repeats || self.play_duration() == Duration::from_millis(0) {
+                return Some(delta);
+            }
+
+            self.iteration += 1;
+            self.restart();
+        }
+    }
+
+    // Plays the current iteration, returning the time left over once it has finished
+    fn play(&mut self, delta: Duration, reversed: bool, context: &mut Context) -> Option<Duration> {
+        match self.kind {
+            Kind::Property {
+                target,
+                ref to,
+                duration,
+                easing,
+                ref mut from,
+                ref mut elapsed,
+            } => {
+                if from.is_none() {
+                    *from = read_target(target, context);
+                    if from.is_none() {
+                        // The target is gone
+                        return Some(delta);
+                    }
+                }
+
+                let remaining = duration.sub(*elapsed);
+                let leftover = if delta < remaining {
+                    *elapsed = elapsed.add(delta);
+                    None
+                } else {
+                    *elapsed = duration;
+                    Some(delta.sub(remaining))
+                };
+
+                let mut progress = if duration == Duration::from_millis(0) {
+                    1.0
+                } else {
+                    seconds(*elapsed) / seconds(duration)
+                };
+                if reversed {
+                    progress = 1.0 - progress;
+                }
+
+                let eased = easing.apply(progress);
+                let from = from.as_ref().unwrap();
+                let values: Vec<f32> = from
+                    .iter()
+                    .enumerate()
+                    .map(|(index, start)| {
+                        let end = to.get(index).cloned().unwrap_or(*start);
+                        return start + (end - start) * eased;
+                    })
+                    .collect();
+                write_target(target, &values, context);
+
+                return leftover;
+            }
+            Kind::Sequence {
+                ref mut tweens,
+                ref mut current,
+            } => {
+                let mut delta = delta;
+                while *current < tweens.len() {
+                    delta = match tweens[*current].advance(delta, context) {
+                        Some(leftover) => leftover,
+                        None => return None,
+                    };
+                    *current += 1;
+                }
+
+                return Some(delta);
+            }
+            Kind::Parallel {
+                ref mut tweens,
+                ref mut leftovers,
+            } => {
+                for (tween, leftover) in tweens.iter_mut().zip(leftovers.iter_mut()) {
+                    if leftover.is_none() {
+                        *leftover = tween.advance(delta, context);
+                    }
+                }
+
+                if leftovers.iter().any(|leftover| leftover.is_none()) {
+                    return None;
+                }
+
+                // The tween that finished last has the least time left over
+                return leftovers.iter().filter_map(|leftover| *leftover).min();
+            }
+        }
+    }
+
+    // Rewinds the tween for another play. Property tweens keep the values they started
+    // from, so repeating jumps back to them.
+    fn restart(&mut self) {
+        match self.kind {
+            Kind::Property {
+                ref mut elapsed, ..
+            } => *elapsed = Duration::from_millis(0),
+            Kind::Sequence {
+                ref mut tweens,
+                ref mut current,
+            } => {
+                *current = 0;
+                for tween in tweens.iter_mut() {
+                    tween.reset();
+                }
+            }
+            Kind::Parallel {
+                ref mut tweens,
+                ref mut leftovers,
+            } => {
+                for (tween, leftover) in tweens.iter_mut().zip(leftovers.iter_mut()) {
+                    tween.reset();
+                    *leftover = None;
+                }
+            }
+        }
+    }
+
+    // Puts a tween inside a group back to how it was before the group started playing
+    fn reset(&mut self) {
+        self.delay_left = self.delay;
+        self.iteration = 0;
+        self.restart();
+    }
+}
+
+/// The running tweens, advanced by the tween system during the pre-update stage
+pub struct Tweens {
+    next_handle: u64,
+    running: BTreeMap<TweenHandle, Tween>,
+}
+
+impl Tweens {
+    pub fn new() -> Tweens {
+        return Tweens {
+            next_handle: 0,
+            running: BTreeMap::new(),
+        };
+    }
+
+    pub fn start(&mut self, tween: Tween) -> TweenHandle {
+        let handle = TweenHandle(self.next_handle);
+        self.next_handle += 1;
+        self.running.insert(handle, tween);
+        return handle;
+    }
+
+    /// Stops the tween where it is, returning whether it was still running
+    pub fn cancel(&mut self, handle: TweenHandle) -> bool {
+        return self.running.remove(&handle).is_some();
+    }
+
+    pub fn is_running(&self, handle: TweenHandle) -> bool {
+        return self.running.contains_key(&handle);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.running.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.running.clear();
+    }
+}
+
+/// Moves every running tween forward by its clock's delta, returning the ones that finished
+pub fn update(context: &mut Context) -> Vec<TweenFinished> {
+    // The tweens are taken out so they can change the rest of the context
+    let mut running = mem::replace(&mut context.tweens.running, BTreeMap::new());
+
+    let mut finished = Vec::new();
+    for (handle, tween) in running.iter_mut() {
+        let delta = context.clock_for(tween.domain).delta();
+        if tween.advance(delta, context).is_some() {
+            finished.push(TweenFinished { handle: *handle });
+        }
+    }
+
+    for event in &finished {
+        running.remove(&event.handle);
+    }
+
+    // Keep tweens cancelled or started while the others were playing as they are now
+    let started = mem::replace(&mut context.tweens.running, BTreeMap::new());
+    context.tweens.running = running;
+    context.tweens.running.extend(started);
+
+    return finished;
+}
+
+fn read_target(target: TweenTarget, context: &mut Context) -> Option<Vec<f32>> {
+    if let TweenTarget::CameraPosition = target {
+        let (x, y) = context.camera.position();
+        return Some(vec![x, y]);
+    }
+
+    let (key, renderable) = target_renderable(target);
+    let entity = match context.entities.get(&key) {
+        Some(entity) => entity,
+        None => return None,
+    };
+
+    if let TweenTarget::Field(_, accessor) = target {
+        return accessor(entity).map(|value| vec![*value]);
+    }
+
+    return entity
+        .get_component::<Renderable>(renderable)
+        .map(|renderable| match target {
+            TweenTarget::Position(_) => vec![renderable.x, renderable.y],
+            TweenTarget::Size(_) => vec![renderable.width, renderable.height],
+            _ => renderable.color.to_vec(),
+        });
+}
+
+fn write_target(target: TweenTarget, values: &[f32], context: &mut Context) {
+    if let TweenTarget::CameraPosition = target {
+        context.camera.set_position(values[0], values[1]);
+        return;
+    }
+
+    let (key, renderable) = target_renderable(target);
+    let entity = match context.entities.get(&key) {
+        Some(entity) => entity,
+        None => return,
+    };
+
+    if let TweenTarget::Field(_, accessor) = target {
+        if let Some(value) = accessor(entity) {
+            *value = values[0];
+        }
+        return;
+    }
+
+    if let Some(renderable) = entity.get_component::<Renderable>(renderable) {
+        match target {
+            TweenTarget::Position(_) => {
+                let z = renderable.z;
+                renderable.set_position(values[0], values[1], z);
+            }
+            TweenTarget::Size(_) => renderable.set_size(values[0], values[1]),
+            _ => renderable.set_color(values[0], values[1], values[2]),
+        }
+    }
+}
+
+// The entity of an entity target and the name of the renderable component
+fn target_renderable(target: TweenTarget) -> (Key, &'static str) {
+    let key = match target {
+        TweenTarget::Position(key)
+        | TweenTarget::Size(key)
+        | TweenTarget::Color(key)
+        | TweenTarget::Field(key, _) => key,
+        TweenTarget::CameraPosition => unreachable!(),
+    };
+
+    return (key, Renderable::name());
+}
+
+fn seconds(duration: Duration) -> f32 {
+    return duration.as_secs() as f32 + duration.subsec_nanos() as f32 * 1e-9;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn_renderable(context: &mut Context) -> Key {
+        let mut entity = Box::new(Entity::new(0));
+        entity.set_component(Renderable::new());
+        return context.spawn(entity);
+    }
+
+    // Ticks the clocks by the step and plays the tweens, returning the finished ones
+    fn step(context: &mut Context, millis: u64) -> Vec<TweenHandle> {
+        for &domain in &[TimeDomain::Real, TimeDomain::Ui, TimeDomain::Game] {
+            let clock = context.clock_for_mut(domain);
+            clock.set_fixed_timestep(Some(Duration::from_millis(millis)));
+            clock.tick();
+        }
+
+        return update(context).iter().map(|event| event.handle).collect();
+    }
+
+    fn x(context: &mut Context, key: Key) -> f32 {
+        let entity = context.entities.get(&key).unwrap();
+        return entity
+            .get_component::<Renderable>(Renderable::name())
+            .unwrap()
+            .x;
+    }
+
+    #[test]
+    fn property_tweens_reach_their_target() {
+        let mut context = Context::new();
+        let key = spawn_renderable(&mut context);
+        let handle = context
+            .tweens
+            .start(Tween::move_to(key, 100.0, 0.0, Duration::from_secs(1)));
+
+        assert!(step(&mut context, 500).is_empty());
+        assert!((x(&mut context, key) - 50.0).abs() < 1e-3);
+        assert_eq!(step(&mut context, 600), vec![handle]);
+        assert_eq!(x(&mut context, key), 100.0);
+        assert!(!context.tweens.is_running(handle));
+    }
+
+    #[test]
+    fn every_easing_starts_and_ends_on_the_values() {
+        let easings = [
+            Easing::Linear,
+            Easing::QuadIn,
+            Easing::QuadOut,
+            Easing::QuadInOut,
+            Easing::CubicIn,
+            Easing::CubicOut,
+            Easing::CubicInOut,
+            Easing::QuartIn,
+            Easing::QuartOut,
+            Easing::QuartInOut,
+            Easing::QuintIn,
+            Easing::QuintOut,
+            Easing::QuintInOut,
+            Easing::SineIn,
+            Easing::SineOut,
+            Easing::SineInOut,
+            Easing::ExpoIn,
+            Easing::ExpoOut,
+            Easing::ExpoInOut,
+            Easing::CircIn,
+            Easing::CircOut,
+            Easing::CircInOut,
+            Easing::BackIn,
+            Easing::BackOut,
+            Easing::BackInOut,
+            Easing::ElasticIn,
+            Easing::ElasticOut,
+            Easing::ElasticInOut,
+            Easing::BounceIn,
+            Easing::BounceOut,
+            Easing::BounceInOut,
+        ];
+
+        for &easing in &easings {
+            let mut context = Context::new();
+            let key = spawn_renderable(&mut context);
+            let tween = Tween::move_to(key, 10.0, 0.0, Duration::from_millis(100));
+            context.tweens.start(tween.easing(easing));
+
+            step(&mut context, 0);
+            assert!(x(&mut context, key).abs() < 1e-3, "{:?}", easing);
+            step(&mut context, 100);
+            assert!((x(&mut context, key) - 10.0).abs() < 1e-3, "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn sequences_play_in_order_and_groups_wait_for_the_longest() {
+        let mut context = Context::new();
+        let key = spawn_renderable(&mut context);
+        let sequence = context.tweens.start(Tween::sequence(vec![
+            Tween::move_to(key, 10.0, 0.0, Duration::from_millis(100)),
+            Tween::move_to(key, 20.0, 0.0, Duration::from_millis(100)),
+        ]));
+        let parallel = context.tweens.start(Tween::parallel(vec![
+            Tween::resize_to(key, 2.0, 2.0, Duration::from_millis(100)),
+            Tween::color_to(key, [1.0, 1.0, 1.0], Duration::from_millis(300)),
+        ]));
+
+        assert!(step(&mut context, 150).is_empty());
+        assert!((x(&mut context, key) - 15.0).abs() < 1e-3);
+        assert_eq!(step(&mut context, 50), vec![sequence]);
+        assert_eq!(x(&mut context, key), 20.0);
+        assert_eq!(step(&mut context, 100), vec![parallel]);
+    }
+
+    #[test]
+    fn yoyo_repeats_play_back_the_other_way() {
+        let mut context = Context::new();
+        let key = spawn_renderable(&mut context);
+        let tween = Tween::move_to(key, 100.0, 0.0, Duration::from_millis(100));
+        let handle = context.tweens.start(tween.repeat(1).yoyo());
+
+        assert!(step(&mut context, 150).is_empty());
+        assert!((x(&mut context, key) - 50.0).abs() < 1e-3);
+        assert_eq!(step(&mut context, 50), vec![handle]);
+        assert_eq!(x(&mut context, key), 0.0);
+    }
+
+    #[test]
+    fn tweens_follow_their_domain() {
+        let mut context = Context::new();
+        let on_ui = spawn_renderable(&mut context);
+        let on_game = spawn_renderable(&mut context);
+        let duration = Duration::from_millis(400);
+        context
+            .tweens
+            .start(Tween::move_to(on_ui, 100.0, 0.0, duration).in_domain(TimeDomain::Ui));
+        context
+            .tweens
+            .start(Tween::move_to(on_game, 100.0, 0.0, duration));
+
+        context.ui_clock.set_time_scale(0.5);
+        context.clock.pause();
+        step(&mut context, 400);
+        assert!((x(&mut context, on_ui) - 50.0).abs() < 1e-3);
+        assert_eq!(x(&mut context, on_game), 0.0);
+    }
+}
@@ -1,11 +1,14 @@
+//! Entities are bags of components, keyed by component name.
+
 use components::Component;
 use std::any::Any;
 use std::collections::HashMap;
 
+/// A thing in the world, made up of components
 #[derive(Debug)]
 pub struct Entity {
     id: u64,
-    components: HashMap<&'static str, Box<Any>>,
+    components: HashMap<&'static str, Box<Component>>,
 }
 
 impl Entity {
@@ -16,16 +19,71 @@ impl Entity {
         };
     }
 
+    pub fn id(&self) -> u64 {
+        return self.id;
+    }
+
     pub fn get_component<T: Component + Any>(&mut self, name: &'static str) -> Option<&mut T> {
         let component = self.components.get_mut(name);
         match component {
-            Some(component) => component.as_mut().downcast_mut(),
+            Some(component) => (**component).as_any_mut().downcast_mut(),
             None => None,
         }
     }
 
     pub fn set_component<T: Component + Any>(&mut self, component: Box<T>) {
-        self.components
-            .insert(component.name(), component as Box<Any>);
+        self.insert_component(component as Box<Component>);
+    }
+
+    pub fn insert_component(&mut self, component: Box<Component>) {
+        self.components.insert(component.name(), component);
+    }
+
+    /// The saved state of every component, in name order
+    pub fn component_states(&self) -> Vec<(&'static str, Vec<u8>)> {
+        let mut states: Vec<(&'static str, Vec<u8>)> = self
+            .components
+            .iter()
+            .map(|(name, component)| {
+                let mut state = Vec::new();
+                component.save_state(&mut state);
+                return (*name, state);
+            })
+            .collect();
+        states.sort_by_key(|&(name, _)| name);
+        return states;
+    }
+
+    /// Loads the states into the existing components. Returns false without changing
+    /// anything if the entity doesn't have exactly these components.
+    pub fn load_component_states(&mut self, states: &[(&'static str, Vec<u8>)]) -> bool {
+        let matching = states.len() == self.components.len()
+            && states
+                .iter()
+                .all(|&(name, _)| self.components.contains_key(name));
+        if !matching {
+            return false;
+        }
+
+        for &(name, ref state) in states {
+            if let Some(component) = self.components.get_mut(name) {
+                component.load_state(state);
+            }
+        }
+
+        return true;
+    }
+
+    /// Writes the id and the state of every component, in name order so the output is
+    /// the same on every run
+    pub fn save_state(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.id.to_le_bytes());
+
+        let mut names: Vec<&&'static str> = self.components.keys().collect();
+        names.sort();
+        for name in names {
+            out.extend_from_slice(name.as_bytes());
+            self.components[*name].save_state(out);
+        }
     }
 }
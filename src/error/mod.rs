@@ -0,0 +1,107 @@
+//! The errors the engine reports from starting up and running a game.
+
+extern crate glutin;
+
+use std::error;
+use std::fmt;
+use std::io;
+
+/// Everything that can stop the engine from starting or keep a game from running
+#[derive(Debug)]
+pub enum EngineError {
+    /// The window or its GL context could not be created, e.g. the requested GL version
+    /// isn't supported
+    WindowCreation(glutin::CreationError),
+    /// The GL context could not be made current
+    ContextActivation(glutin::ContextError),
+    /// The GL context was lost, e.g. after a driver reset
+    ContextLost,
+    SwapBuffers(glutin::ContextError),
+    ShaderCompile {
+        stage: &'static str,
+        log: String,
+    },
+    ProgramLink {
+        log: String,
+    },
+    Io(io::Error),
+    /// A plugin could not be built, e.g. because a plugin it depends on is missing
+    Plugin {
+        name: &'static str,
+        message: String,
+    },
+    /// A replay file could not be understood
+    Replay {
+        line: usize,
+        message: String,
+    },
+    /// An animation clip file could not be understood
+    Clip {
+        line: usize,
+        message: String,
+    },
+    /// Errors reported by games and systems themselves
+    Game(Box<error::Error>),
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match *self {
+            EngineError::WindowCreation(ref err) => write!(f, "Could not create window: {}", err),
+            EngineError::ContextActivation(ref err) => {
+                write!(f, "Could not activate GL context: {}", err)
+            }
+            EngineError::ContextLost => write!(f, "The GL context was lost"),
+            EngineError::SwapBuffers(ref err) => write!(f, "Could not swap buffers: {}", err),
+            EngineError::ShaderCompile { stage, ref log } => {
+                write!(f, "Could not compile {} shader: {}", stage, log)
+            }
+            EngineError::ProgramLink { ref log } => write!(f, "Could not link program: {}", log),
+            EngineError::Io(ref err) => write!(f, "{}", err),
+            EngineError::Plugin { name, ref message } => {
+                write!(f, "Could not build plugin {}: {}", name, message)
+            }
+            EngineError::Replay { line, ref message } => {
+                write!(f, "Invalid replay on line {}: {}", line, message)
+            }
+            EngineError::Clip { line, ref message } => {
+                write!(f, "Invalid animation clip on line {}: {}", line, message)
+            }
+            EngineError::Game(ref err) => write!(f, "{}", err),
+        };
+    }
+}
+
+impl error::Error for EngineError {
+    fn source(&self) -> Option<&(error::Error + 'static)> {
+        return match *self {
+            EngineError::WindowCreation(ref err) => Some(err),
+            EngineError::ContextActivation(ref err) => Some(err),
+            EngineError::SwapBuffers(ref err) => Some(err),
+            EngineError::Io(ref err) => Some(err),
+            EngineError::Game(ref err) => Some(err.as_ref()),
+            _ => None,
+        };
+    }
+}
+
+impl From<glutin::CreationError> for EngineError {
+    fn from(err: glutin::CreationError) -> EngineError {
+        return EngineError::WindowCreation(err);
+    }
+}
+
+impl From<io::Error> for EngineError {
+    fn from(err: io::Error) -> EngineError {
+        return EngineError::Io(err);
+    }
+}
+
+impl From<glutin::ContextError> for EngineError {
+    fn from(err: glutin::ContextError) -> EngineError {
+        return match err {
+            glutin::ContextError::ContextLost => EngineError::ContextLost,
+            err => EngineError::ContextActivation(err),
+        };
+    }
+}
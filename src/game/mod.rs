@@ -1,20 +1,110 @@
+//! Running games: the `Game` trait, the `GameRunner` frame loop, plugins, states, replays
+//! and rewinding.
+
+#[cfg(feature = "rendering")]
 extern crate gl;
 extern crate glutin;
 
+pub mod plugin;
+pub mod replay;
+pub mod rewind;
+pub mod state;
+
+use self::plugin::Plugin;
+use self::replay::{Replay, ReplayEvent};
+use self::rewind::{ComponentFactory, Rewind};
+use context::events::Events;
+use context::input::InputEvent;
+use context::resources::Resources;
 use context::Context;
+use error::EngineError;
+use std::any::Any;
+use std::mem;
+use std::path::PathBuf;
 use std::thread::sleep;
 use std::time::Duration;
-use std::time::SystemTime;
-use systems::System;
+use systems::animation::AnimationPlugin;
+use systems::camera::CameraPlugin;
+use systems::input::InputPlugin;
+#[cfg(feature = "rendering")]
+use systems::rendering::RenderingPlugin;
+use systems::tasks::TaskPlugin;
+use systems::time::TimePlugin;
+use systems::timers::TimerPlugin;
+use systems::tween::TweenPlugin;
+use systems::{Stage, System};
+use util::log;
+use util::trace;
 
+/// A game, driven by the `GameRunner`
 pub trait Game {
-    fn initialize(&mut self, context: &mut Context);
+    fn initialize(&mut self, context: &mut Context) -> Result<(), EngineError>;
     fn game_loop(&mut self, context: &mut Context);
+
+    // Lifecycle hooks. These are optional; the defaults do nothing.
+    fn on_resize(&mut self, _context: &mut Context, _width: u32, _height: u32) {}
+    fn on_focus_changed(&mut self, _context: &mut Context, _focused: bool) {}
+    fn on_pause(&mut self, _context: &mut Context) {}
+    fn on_resume(&mut self, _context: &mut Context) {}
+    fn on_exit(&mut self, _context: &mut Context) {}
 }
 
+const FRAME_PERIOD_MS: u64 = 16;
+const IDLE_FRAME_PERIOD_MS: u64 = 100;
+// Recordings use a fixed timestep so they can be played back exactly
+const DEFAULT_REPLAY_TIMESTEP_NS: u32 = 16_666_667;
+// Ten seconds of frames at 60 fps
+const DEFAULT_REWIND_CAPACITY: usize = 600;
+// The screen size games see when running headless
+const HEADLESS_SCREEN: (u32, u32) = (1280, 720);
+
+/// Owns the window, the systems and the game, and runs the frame loop until the game exits
 pub struct GameRunner {
     game: Box<Game>,
     systems: Vec<Box<System>>,
+
+    // Plugins waiting to be built, and what they added that needs a context to live in
+    plugins: Vec<Box<Plugin>>,
+    built_plugins: Vec<&'static str>,
+    resources: Resources,
+    event_updates: Vec<fn(&mut Resources)>,
+
+    // Window activity
+    pause_on_focus_loss: bool,
+    focused: bool,
+    minimized: bool,
+    suspended: bool,
+    paused: bool,
+    paused_clock: bool,
+
+    // Trace export
+    trace_path: PathBuf,
+    trace_hotkey: Option<glutin::VirtualKeyCode>,
+    trace_on_exit: bool,
+
+    gl_request: glutin::GlRequest,
+    window_size: Option<(u32, u32)>,
+    vsync: bool,
+    frame_period: Option<Duration>,
+    headless: bool,
+    seed: Option<u64>,
+    fixed_timestep: Option<Duration>,
+
+    // Input gathered from the window since the last frame
+    pending_input: Vec<InputEvent>,
+
+    // Replays
+    record_path: Option<PathBuf>,
+    replay_path: Option<PathBuf>,
+    verify_replay: bool,
+    recording: Option<Replay>,
+    playback: Option<Replay>,
+    replay_divergence: Option<u64>,
+
+    // Rewinding
+    rewind_hotkey: Option<glutin::VirtualKeyCode>,
+    rewind: Rewind,
+    rewind_paused_clock: bool,
 }
 
 impl GameRunner {
@@ -22,6 +112,43 @@ impl GameRunner {
         return GameRunner {
             game: game,
             systems: Vec::new(),
+
+            plugins: Vec::new(),
+            built_plugins: Vec::new(),
+            resources: Resources::new(),
+            event_updates: Vec::new(),
+
+            pause_on_focus_loss: true,
+            focused: true,
+            minimized: false,
+            suspended: false,
+            paused: false,
+            paused_clock: false,
+
+            trace_path: PathBuf::from("trace.json"),
+            trace_hotkey: None,
+            trace_on_exit: false,
+
+            gl_request: glutin::GlRequest::Latest,
+            window_size: None,
+            vsync: true,
+            frame_period: Some(Duration::from_millis(FRAME_PERIOD_MS)),
+            headless: false,
+            seed: None,
+            fixed_timestep: None,
+
+            pending_input: Vec::new(),
+
+            record_path: None,
+            replay_path: None,
+            verify_replay: false,
+            recording: None,
+            playback: None,
+            replay_divergence: None,
+
+            rewind_hotkey: None,
+            rewind: Rewind::new(DEFAULT_REWIND_CAPACITY),
+            rewind_paused_clock: false,
         };
     }
 
@@ -29,81 +156,726 @@ impl GameRunner {
         self.systems.push(system)
     }
 
-    pub fn start(&mut self) {
-        use glutin::GlContext;
+    pub fn add_plugin(&mut self, plugin: Box<Plugin>) {
+        self.plugins.push(plugin);
+    }
 
-        let mut events_loop = glutin::EventsLoop::new();
-        let window = glutin::WindowBuilder::new();
-        let window_context = glutin::ContextBuilder::new().with_vsync(true);
-        let gl_window = glutin::GlWindow::new(window, window_context, &events_loop).unwrap();
+    /// The engine's own time, timer, input and rendering plugins
+    pub fn add_default_plugins(&mut self) {
+        self.add_plugin(Box::new(TimePlugin {}));
+        self.add_plugin(Box::new(InputPlugin {}));
+        self.add_plugin(Box::new(TimerPlugin {}));
+        self.add_plugin(Box::new(TweenPlugin {}));
+        self.add_plugin(Box::new(AnimationPlugin {}));
+        self.add_plugin(Box::new(TaskPlugin {}));
+        self.add_plugin(Box::new(CameraPlugin {}));
+        #[cfg(feature = "rendering")]
+        self.add_plugin(Box::new(RenderingPlugin {}));
+    }
 
-        unsafe { gl_window.make_current().unwrap() };
+    pub fn has_plugin(&self, name: &str) -> bool {
+        return self.built_plugins.iter().any(|built| *built == name);
+    }
 
-        gl::load_with(|symbol| gl_window.get_proc_address(symbol) as *const _);
+    /// Makes the value available as `context.resources` once the game starts
+    pub fn insert_resource<T: Any>(&mut self, value: T) {
+        self.resources.insert(value);
+    }
 
-        let mut context = Context::new();
-        self.update_screen_dimensions(&mut context, &gl_window);
+    /// Lets events of this type be sent through the context
+    pub fn add_event<T: Any>(&mut self) {
+        if self.resources.contains::<Events<T>>() {
+            return;
+        }
 
-        for system in &mut self.systems {
-            system.initialize();
+        self.resources.insert(Events::<T>::new());
+        self.event_updates.push(update_events::<T>);
+    }
+
+    /// Whether game time stops while the window is unfocused or minimized. On by default.
+    pub fn set_pause_on_focus_loss(&mut self, pause_on_focus_loss: bool) {
+        self.pause_on_focus_loss = pause_on_focus_loss;
+    }
+
+    /// Where the recent frames' profiling scopes are written, as Chrome trace event JSON
+    pub fn set_trace_path<P: Into<PathBuf>>(&mut self, path: P) {
+        self.trace_path = path.into();
+    }
+
+    pub fn set_trace_hotkey(&mut self, key: Option<glutin::VirtualKeyCode>) {
+        self.trace_hotkey = key;
+    }
+
+    pub fn set_trace_on_exit(&mut self, trace_on_exit: bool) {
+        self.trace_on_exit = trace_on_exit;
+    }
+
+    /// Which GL version to ask for. Launchers can retry `start` with an older version if
+    /// window creation fails.
+    pub fn set_gl_request(&mut self, gl_request: glutin::GlRequest) {
+        self.gl_request = gl_request;
+    }
+
+    /// The window's initial size in logical pixels, which is also the screen size when
+    /// running headless
+    pub fn set_window_size(&mut self, width: u32, height: u32) {
+        self.window_size = Some((width, height));
+    }
+
+    /// On by default
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.vsync = vsync;
+    }
+
+    /// How long a frame should take at least. The runner sleeps for whatever is left of it,
+    /// and with `None` runs frames back to back, e.g. for benchmarks.
+    pub fn set_frame_period(&mut self, frame_period: Option<Duration>) {
+        self.frame_period = frame_period;
+    }
+
+    /// Runs without a window or GL context. Always the case without the rendering feature.
+    pub fn set_headless(&mut self, headless: bool) {
+        self.headless = headless;
+    }
+
+    pub fn is_headless(&self) -> bool {
+        return self.headless || cfg!(not(feature = "rendering"));
+    }
+
+    /// Fixes the seed of `context.random`. Without one, every run is seeded differently.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    /// Advances game and UI time by exactly this much every frame instead of following the
+    /// wall clock
+    pub fn set_fixed_timestep(&mut self, fixed_timestep: Option<Duration>) {
+        self.fixed_timestep = fixed_timestep;
+    }
+
+    /// Records the seed, timestep, window size and every input event of this run to a
+    /// replay file, written when the game exits
+    pub fn record_replay<P: Into<PathBuf>>(&mut self, path: P) {
+        self.record_path = Some(path.into());
+    }
+
+    /// Plays a recorded replay instead of listening to the window's input. With
+    /// verification on, the world is hashed every frame and compared to the recording.
+    pub fn play_replay<P: Into<PathBuf>>(&mut self, path: P, verify: bool) {
+        self.replay_path = Some(path.into());
+        self.verify_replay = verify;
+    }
+
+    /// The first frame whose world hash didn't match the replay being verified
+    pub fn replay_divergence(&self) -> Option<u64> {
+        return self.replay_divergence;
+    }
+
+    /// With a hotkey set, the last frames of the world are kept. Pressing it pauses the
+    /// game, the left and right arrows then scrub through the frames and pressing it again
+    /// continues from the current one. Hold shift to scrub ten frames at a time.
+    pub fn set_rewind_hotkey(&mut self, key: Option<glutin::VirtualKeyCode>) {
+        self.rewind_hotkey = key;
+    }
+
+    pub fn set_rewind_capacity(&mut self, frames: usize) {
+        self.rewind = Rewind::new(frames);
+    }
+
+    /// Lets entities with this component be rebuilt when rewinding
+    pub fn register_rewind_component(&mut self, name: &'static str, factory: ComponentFactory) {
+        self.rewind.register_component(name, factory);
+    }
+
+    pub fn start(&mut self) -> Result<(), EngineError> {
+        self.build_plugins()?;
+
+        if let Some(ref path) = self.replay_path {
+            let replay = Replay::load(path)?;
+            vlog_info!(
+                "Playing replay {} ({} frames)",
+                path.display(),
+                replay.length
+            );
+            self.playback = Some(replay);
+        }
+
+        if self.headless {
+            return self.run_headless();
         }
 
-        self.game.initialize(&mut context);
+        return self.run_windowed();
+    }
+
+    #[cfg(not(feature = "rendering"))]
+    fn run_windowed(&mut self) -> Result<(), EngineError> {
+        vlog_warn!("Built without rendering, running headless");
+        return self.run_headless();
+    }
 
+    #[cfg(feature = "rendering")]
+    fn run_windowed(&mut self) -> Result<(), EngineError> {
+        use glutin::GlContext;
+
+        let mut events_loop = glutin::EventsLoop::new();
+        let mut window = glutin::WindowBuilder::new();
+        if let Some((width, height)) = self.window_size {
+            window =
+                window.with_dimensions(glutin::dpi::LogicalSize::new(width as f64, height as f64));
+        }
+        let window_context = glutin::ContextBuilder::new()
+            .with_gl(self.gl_request)
+            .with_vsync(self.vsync);
+        let gl_window = glutin::GlWindow::new(window, window_context, &events_loop)?;
+
+        unsafe { gl_window.make_current()? };
+
+        gl::load_with(|symbol| gl_window.get_proc_address(symbol) as *const _);
+
+        let mut context = self.create_context(screen_dimensions(&gl_window));
+        let dpi = gl_window.get_current_monitor().get_hidpi_factor();
+        context.screen.set_dpi_factor(dpi as f32);
+        self.initialize(&mut context)?;
+
+        let mut result = Ok(());
         let mut frame_count = 0;
-        let frame_period = Duration::from_millis(16);
         let event_loop_proxy = events_loop.create_proxy();
         events_loop.run_forever(|event| {
-            use glutin::{ControlFlow, Event, WindowEvent};
+            use glutin::{ControlFlow, ElementState, Event, MouseScrollDelta, WindowEvent};
 
-            if let Event::WindowEvent { event, .. } = event {
-                if let WindowEvent::CloseRequested = event {
-                    return ControlFlow::Break;
+            match event {
+                Event::WindowEvent { event, .. } => match event {
+                    WindowEvent::CloseRequested => return ControlFlow::Break,
+                    WindowEvent::Focused(focused) => {
+                        self.focused = focused;
+                        self.game.on_focus_changed(&mut context, focused);
+                        self.update_paused(&mut context);
+                    }
+                    WindowEvent::Resized(size) => {
+                        // Minimizing shrinks the window to nothing on some platforms
+                        self.minimized = size.width == 0.0 || size.height == 0.0;
+                        self.update_paused(&mut context);
+                    }
+                    WindowEvent::HiDpiFactorChanged(dpi) => {
+                        context.screen.set_dpi_factor(dpi as f32);
+                    }
+                    WindowEvent::KeyboardInput { input, .. } => {
+                        let pressed = input.state == ElementState::Pressed;
+                        if let Some(key) = input.virtual_keycode {
+                            self.pending_input.push(if pressed {
+                                InputEvent::KeyPressed(key.into())
+                            } else {
+                                InputEvent::KeyReleased(key.into())
+                            });
+                        }
+
+                        if pressed
+                            && self.trace_hotkey.is_some()
+                            && input.virtual_keycode == self.trace_hotkey
+                        {
+                            self.write_trace();
+                        }
+                    }
+                    WindowEvent::CursorMoved { position, .. } => {
+                        let dpi = gl_window.get_current_monitor().get_hidpi_factor();
+                        let position = position.to_physical(dpi);
+                        self.pending_input.push(InputEvent::MouseMoved {
+                            x: position.x as f32,
+                            y: position.y as f32,
+                        });
+                    }
+                    WindowEvent::MouseInput { state, button, .. } => {
+                        self.pending_input.push(match state {
+                            ElementState::Pressed => InputEvent::MousePressed(button.into()),
+                            ElementState::Released => InputEvent::MouseReleased(button.into()),
+                        });
+                    }
+                    WindowEvent::MouseWheel { delta, .. } => {
+                        let (dx, dy) = match delta {
+                            MouseScrollDelta::LineDelta(dx, dy) => (dx, dy),
+                            MouseScrollDelta::PixelDelta(position) => {
+                                (position.x as f32, position.y as f32)
+                            }
+                        };
+                        self.pending_input.push(InputEvent::MouseWheel { dx, dy });
+                    }
+                    _ => (),
+                },
+                Event::Suspended(suspended) => {
+                    self.suspended = suspended;
+                    self.update_paused(&mut context);
                 }
+                _ => (),
             }
 
-            let now = SystemTime::now();
-            self.update_screen_dimensions(&mut context, &gl_window);
-            self.game.game_loop(&mut context);
+            let frame_scope = begin_frame(frame_count);
+            self.update(frame_count, &mut context, screen_dimensions(&gl_window));
 
-            for system in &mut self.systems {
-                system.apply(&mut context)
+            let scope = trace::scope("swap_buffers");
+            if let Err(err) = gl_window.swap_buffers() {
+                result = Err(match err {
+                    glutin::ContextError::ContextLost => EngineError::ContextLost,
+                    err => EngineError::SwapBuffers(err),
+                });
+                return ControlFlow::Break;
             }
+            context.profiler.record("swap_buffers", scope.end());
 
-            gl_window.swap_buffers().unwrap();
+            if context.is_exit_requested() {
+                return ControlFlow::Break;
+            }
 
             frame_count += 1;
-            let duration = match now.elapsed() {
-                Ok(duration) => duration,
+            self.end_frame(&mut context, frame_scope);
+
+            if let Err(err) = event_loop_proxy.wakeup() {
+                vlog_error!("Error waking up event loop {:?}", err);
+                return ControlFlow::Break;
+            }
+
+            ControlFlow::Continue
+        });
+
+        return self.finish(&mut context, result);
+    }
+
+    // Runs the game without a window or GL context, e.g. on a server
+    fn run_headless(&mut self) -> Result<(), EngineError> {
+        let screen = self.window_size.unwrap_or(HEADLESS_SCREEN);
+        let mut context = self.create_context(Some(screen));
+        self.initialize(&mut context)?;
+
+        let mut frame_count = 0;
+        loop {
+            let frame_scope = begin_frame(frame_count);
+            self.update(frame_count, &mut context, None);
+            if context.is_exit_requested() {
+                break;
+            }
+
+            frame_count += 1;
+            self.end_frame(&mut context, frame_scope);
+        }
+
+        return self.finish(&mut context, Ok(()));
+    }
+
+    fn create_context(&mut self, screen: Option<(u32, u32)>) -> Context {
+        let mut context = Context::new();
+        context.resources = mem::replace(&mut self.resources, Resources::new());
+        match self.playback {
+            Some(ref replay) => {
+                context.random.reseed(replay.seed);
+                context.clock.set_fixed_timestep(Some(replay.timestep));
+                context.ui_clock.set_fixed_timestep(Some(replay.timestep));
+                set_screen_dimensions(&mut context, replay.screen);
+            }
+            None => {
+                if let Some(seed) = self.seed {
+                    context.random.reseed(seed);
+                }
+
+                let mut fixed_timestep = self.fixed_timestep;
+                if self.record_path.is_some() && fixed_timestep.is_none() {
+                    fixed_timestep = Some(Duration::new(0, DEFAULT_REPLAY_TIMESTEP_NS));
+                }
+                context.clock.set_fixed_timestep(fixed_timestep);
+                context.ui_clock.set_fixed_timestep(fixed_timestep);
+
+                if let Some(dimensions) = screen {
+                    set_screen_dimensions(&mut context, dimensions);
+                }
+
+                if let (Some(_), Some(timestep)) = (self.record_path.as_ref(), fixed_timestep) {
+                    let screen = (context.screen.width, context.screen.height);
+                    self.recording = Some(Replay::new(context.random.seed(), timestep, screen));
+                }
+            }
+        }
+        vlog_info!("Random seed is {}", context.random.seed());
+
+        return context;
+    }
+
+    // On failure the resources go back to the runner, so `start` can be called again
+    fn initialize(&mut self, context: &mut Context) -> Result<(), EngineError> {
+        for index in 0..self.systems.len() {
+            if let Err(err) = self.systems[index].initialize() {
+                for system in self.systems[..index].iter_mut().rev() {
+                    system.shutdown();
+                }
+                self.resources = mem::replace(&mut context.resources, Resources::new());
+                return Err(err);
+            }
+        }
+
+        if let Err(err) = self.game.initialize(context) {
+            self.shutdown_systems();
+            self.resources = mem::replace(&mut context.resources, Resources::new());
+            return Err(err);
+        }
+
+        return Ok(());
+    }
+
+    // Everything a frame does apart from presenting it
+    fn update(&mut self, frame: u64, context: &mut Context, screen: Option<(u32, u32)>) {
+        for update in &self.event_updates {
+            update(&mut context.resources);
+        }
+
+        let events = self.frame_events(frame, context, screen);
+        for event in &events {
+            match *event {
+                ReplayEvent::Input(event) => context.send_event(event),
+                ReplayEvent::Resize(width, height) => {
+                    set_screen_dimensions(context, (width, height));
+                    self.game.on_resize(context, width, height);
+                }
+            }
+        }
+        self.run_systems(Stage::PreUpdate, context);
+
+        // While scrubbing the world is frozen, only the systems run
+        let scrubbing = self.update_rewind(context);
+        if !scrubbing {
+            let scope = trace::scope("game_loop");
+            self.game.game_loop(context);
+            context.profiler.record("game_loop", scope.end());
+        }
+
+        self.run_systems(Stage::PostUpdate, context);
+
+        if !scrubbing {
+            self.check_replay(frame, events, context);
+            if self.rewind_hotkey.is_some() {
+                let scope = trace::scope("rewind_capture");
+                self.rewind.capture(frame, context);
+                context.profiler.record("rewind_capture", scope.end());
+            }
+        }
+    }
+
+    // Records the frame's duration and sleeps for the rest of the frame period
+    fn end_frame(&mut self, context: &mut Context, frame_scope: trace::Scope) {
+        let duration = frame_scope.end();
+        context.profiler.record("frame", duration);
+
+        let frame_period = if self.paused {
+            Duration::from_millis(IDLE_FRAME_PERIOD_MS)
+        } else {
+            match self.frame_period {
+                Some(frame_period) => frame_period,
+                None => return,
+            }
+        };
+
+        if duration < frame_period {
+            let scope = trace::scope("sleep");
+            sleep(frame_period - duration);
+            context.profiler.record("sleep", scope.end());
+        }
+    }
+
+    fn finish(
+        &mut self,
+        context: &mut Context,
+        mut result: Result<(), EngineError>,
+    ) -> Result<(), EngineError> {
+        self.game.on_exit(context);
+
+        if self.trace_on_exit {
+            self.write_trace();
+        }
+
+        if let (Some(recording), Some(path)) = (self.recording.take(), self.record_path.as_ref()) {
+            match recording.save(path) {
+                Ok(_) => vlog_info!("Saved replay to {}", path.display()),
                 Err(err) => {
-                    println!("Could not compute frame duration {:?}", err);
-                    Duration::from_millis(16)
+                    vlog_error!("Could not save replay {}", err);
+                    if result.is_ok() {
+                        result = Err(err);
+                    }
                 }
-            };
+            }
+        }
+
+        self.shutdown_systems();
+        log::flush();
+
+        return result;
+    }
 
-            println!("Frame duration was {:?}", duration);
+    fn run_systems(&mut self, stage: Stage, context: &mut Context) {
+        for system in &mut self.systems {
+            if system.stage() != stage {
+                continue;
+            }
 
-            if duration < frame_period {
-                sleep(frame_period - duration);
+            let scope = trace::scope(system.name());
+            system.apply(context);
+            context.profiler.record(system.name(), scope.end());
+        }
+    }
+
+    // Builds the added plugins, each after the plugins it depends on. Plugins may add
+    // further plugins while being built.
+    fn build_plugins(&mut self) -> Result<(), EngineError> {
+        let mut pending: Vec<Box<Plugin>> = Vec::new();
+        loop {
+            pending.extend(self.plugins.drain(..));
+            if pending.is_empty() {
+                return Ok(());
             }
 
-            match event_loop_proxy.wakeup() {
-                Ok(_) => (),
-                Err(_) => panic!("Error waking up event loop"),
+            let ready = pending.iter().position(|plugin| {
+                return plugin
+                    .dependencies()
+                    .iter()
+                    .all(|dependency| self.has_plugin(dependency));
+            });
+
+            let mut plugin = match ready {
+                Some(index) => pending.remove(index),
+                None => {
+                    let plugin = &pending[0];
+                    let missing: Vec<&'static str> = plugin
+                        .dependencies()
+                        .into_iter()
+                        .filter(|dependency| !self.has_plugin(dependency))
+                        .collect();
+                    let cycle = missing
+                        .iter()
+                        .all(|dependency| pending.iter().any(|other| other.name() == *dependency));
+                    return Err(EngineError::Plugin {
+                        name: plugin.name(),
+                        message: if cycle {
+                            format!("its dependencies on {} form a cycle", missing.join(", "))
+                        } else {
+                            format!("it depends on {}, which was not added", missing.join(", "))
+                        },
+                    });
+                }
             };
 
-            ControlFlow::Continue
-        })
+            if self.has_plugin(plugin.name()) {
+                vlog_warn!("Plugin {} was added more than once", plugin.name());
+                continue;
+            }
+
+            vlog_debug!("Building plugin {}", plugin.name());
+            plugin.build(self);
+            self.built_plugins.push(plugin.name());
+        }
+    }
+
+    // Systems are shut down in reverse order while the GL context is still current
+    fn shutdown_systems(&mut self) {
+        for system in self.systems.iter_mut().rev() {
+            system.shutdown();
+        }
+    }
+
+    fn write_trace(&self) {
+        match trace::write_chrome_trace(&self.trace_path) {
+            Ok(_) => vlog_info!("Wrote trace to {}", self.trace_path.display()),
+            Err(err) => vlog_error!("Could not write trace {:?}", err),
+        }
+    }
+
+    // This frame's events, either gathered from the window or read from the replay
+    fn frame_events(
+        &mut self,
+        frame: u64,
+        context: &Context,
+        screen: Option<(u32, u32)>,
+    ) -> Vec<ReplayEvent> {
+        let pending_input = self.pending_input.drain(..);
+        if let Some(ref replay) = self.playback {
+            return match replay.frame(frame) {
+                Some(replay_frame) => replay_frame.events.clone(),
+                None => Vec::new(),
+            };
+        }
+
+        let mut events: Vec<ReplayEvent> = pending_input.map(ReplayEvent::Input).collect();
+        if let Some((width, height)) = screen {
+            if (width, height) != (context.screen.width, context.screen.height) {
+                events.push(ReplayEvent::Resize(width, height));
+            }
+        }
+
+        return events;
     }
 
-    fn update_screen_dimensions(&mut self, context: &mut Context, window: &glutin::GlWindow) {
-        let dpi = window.get_current_monitor().get_hidpi_factor();
-        if let Some(screen_size) = window
-            .get_inner_size()
-            .map({ |value| value.to_physical(dpi) })
-        {
-            let dimensions: (u32, u32) = screen_size.into();
-            context.screen.set_dimensions(dimensions.0, dimensions.1);
+    // Records the frame, or checks it against the replay being verified
+    fn check_replay(&mut self, frame: u64, events: Vec<ReplayEvent>, context: &mut Context) {
+        if let Some(ref mut recording) = self.recording {
+            recording.record(frame, events, Some(replay::world_hash(context)));
         }
+
+        if let Some(ref replay) = self.playback {
+            let expected = replay
+                .frame(frame)
+                .and_then(|replay_frame| replay_frame.hash);
+            if let (true, Some(expected)) = (self.verify_replay, expected) {
+                if self.replay_divergence.is_none() && replay::world_hash(context) != expected {
+                    vlog_error!("Replay diverged from the recording at frame {}", frame);
+                    self.replay_divergence = Some(frame);
+                }
+            }
+
+            if frame + 1 >= replay.length {
+                vlog_info!("Replay finished after {} frames", replay.length);
+                context.request_exit();
+            }
+        }
+    }
+
+    // Handles the rewind hotkeys, returning whether the world is being scrubbed
+    fn update_rewind(&mut self, context: &mut Context) -> bool {
+        use glutin::VirtualKeyCode;
+
+        let hotkey = match self.rewind_hotkey {
+            Some(hotkey) => hotkey,
+            None => return false,
+        };
+
+        if context.input.was_key_pressed(hotkey) {
+            if self.recording.is_some() || self.playback.is_some() {
+                vlog_warn!("Rewinding is not available while recording or playing a replay");
+            } else if self.rewind.is_scrubbing() {
+                if let Some(frame) = self.rewind.resume() {
+                    vlog_info!("Resuming from frame {}", frame);
+                }
+                if self.rewind_paused_clock {
+                    context.clock.resume();
+                    self.rewind_paused_clock = false;
+                }
+                return false;
+            } else if let Some(frame) = self.rewind.begin_scrub() {
+                vlog_info!("Paused for rewinding at frame {}", frame);
+                if !context.clock.is_paused() {
+                    context.clock.pause();
+                    self.rewind_paused_clock = true;
+                }
+            }
+        }
+
+        if !self.rewind.is_scrubbing() {
+            return false;
+        }
+
+        let shift = context.input.is_key_down(VirtualKeyCode::LShift)
+            || context.input.is_key_down(VirtualKeyCode::RShift);
+        let step = if shift { 10 } else { 1 };
+        let mut frames = 0;
+        if context.input.was_key_pressed(VirtualKeyCode::Left) {
+            frames -= step;
+        }
+        if context.input.was_key_pressed(VirtualKeyCode::Right) {
+            frames += step;
+        }
+        if frames != 0 {
+            if let Some(frame) = self.rewind.step(frames, context) {
+                vlog_info!("Rewound to frame {}", frame);
+            }
+        }
+
+        return true;
+    }
+
+    fn update_paused(&mut self, context: &mut Context) {
+        // Pausing would change game time in ways the replay can't reproduce
+        let replaying = self.recording.is_some() || self.playback.is_some();
+        let inactive = !self.focused || self.minimized;
+        let paused = self.suspended || (self.pause_on_focus_loss && inactive && !replaying);
+        if paused == self.paused {
+            return;
+        }
+
+        self.paused = paused;
+        if paused {
+            // Leave the clock alone if the game already paused it itself
+            if !context.clock.is_paused() {
+                context.clock.pause();
+                self.paused_clock = true;
+            }
+            self.game.on_pause(context);
+        } else {
+            if self.paused_clock {
+                context.clock.resume();
+                self.paused_clock = false;
+            }
+            self.game.on_resume(context);
+        }
+    }
+}
+
+// Starts the frame's logging and tracing, returning the scope timing the whole frame
+fn begin_frame(frame: u64) -> trace::Scope {
+    log::set_frame(frame);
+    trace::begin_frame(frame);
+    return trace::scope("frame");
+}
+
+// The window's inner size in physical pixels
+#[cfg(feature = "rendering")]
+fn screen_dimensions(window: &glutin::GlWindow) -> Option<(u32, u32)> {
+    let dpi = window.get_current_monitor().get_hidpi_factor();
+    return window
+        .get_inner_size()
+        .map({ |value| value.to_physical(dpi).into() });
+}
+
+fn set_screen_dimensions(context: &mut Context, dimensions: (u32, u32)) {
+    context.screen.set_dimensions(dimensions.0, dimensions.1);
+    context.camera.resize(dimensions.0, dimensions.1);
+}
+
+fn update_events<T: Any>(resources: &mut Resources) {
+    if let Some(events) = resources.get_mut::<Events<T>>() {
+        events.update();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FailingGame {}
+
+    impl Game for FailingGame {
+        fn initialize(&mut self, _context: &mut Context) -> Result<(), EngineError> {
+            return Err(EngineError::Game("no save file".into()));
+        }
+
+        fn game_loop(&mut self, _context: &mut Context) {}
+    }
+
+    #[test]
+    fn failed_starts_keep_the_resources() {
+        let mut runner = GameRunner::new(Box::new(FailingGame {}));
+        runner.set_headless(true);
+        runner.add_event::<u32>();
+
+        assert!(runner.start().is_err());
+        assert!(runner.resources.contains::<Events<u32>>());
+    }
+
+    #[test]
+    fn timers_and_tasks_run_after_input() {
+        let mut runner = GameRunner::new(Box::new(FailingGame {}));
+        runner.add_plugin(Box::new(TaskPlugin {}));
+        runner.add_plugin(Box::new(TimerPlugin {}));
+        runner.add_plugin(Box::new(TweenPlugin {}));
+        runner.add_plugin(Box::new(TimePlugin {}));
+        runner.add_plugin(Box::new(InputPlugin {}));
+        runner.build_plugins().unwrap();
+
+        let order: Vec<&str> = runner.systems.iter().map(|system| system.name()).collect();
+        let position = |name| order.iter().position(|&other| other == name).unwrap();
+        assert!(position("input") < position("timers"));
+        assert!(position("input") < position("tasks"));
     }
 }
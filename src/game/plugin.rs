@@ -0,0 +1,15 @@
+use game::GameRunner;
+
+/// A bundle of systems, resources, event types and setup that can be added to a game as one.
+/// Plugins are built when the game starts, after the plugins they depend on.
+pub trait Plugin {
+    /// Identifies the plugin to the plugins depending on it
+    fn name(&self) -> &'static str;
+
+    fn build(&mut self, runner: &mut GameRunner);
+
+    /// Names of the plugins that have to be built first
+    fn dependencies(&self) -> Vec<&'static str> {
+        return Vec::new();
+    }
+}
@@ -0,0 +1,327 @@
+use context::input::{InputEvent, KeyCode, MouseButton};
+use context::Context;
+use error::EngineError;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::SplitWhitespace;
+use std::time::Duration;
+use util::hash::Fnv;
+use util::lines::parse;
+
+const HEADER: &'static str = "violetta-replay 1";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplayEvent {
+    Input(InputEvent),
+    /// The window was resized to this many physical pixels
+    Resize(u32, u32),
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayFrame {
+    pub events: Vec<ReplayEvent>,
+    /// Hash of the world at the end of the frame, see `world_hash`
+    pub hash: Option<u64>,
+}
+
+/// Everything needed to play a run back exactly: the seed, the fixed timestep, the initial
+/// window size and every event keyed by the frame it was handled in.
+///
+/// Replays are stored as text, one line per event:
+///
+/// ```text
+/// violetta-replay 1
+/// seed 1234
+/// timestep 16666667
+/// screen 1280 720
+/// length 600
+/// 12 key_pressed 57
+/// 12 mouse_moved 640.5 360
+/// 12 hash 9c3ae0e2d1f3a4b5
+/// ```
+pub struct Replay {
+    pub seed: u64,
+    pub timestep: Duration,
+    pub screen: (u32, u32),
+    /// Number of frames recorded
+    pub length: u64,
+    frames: BTreeMap<u64, ReplayFrame>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, timestep: Duration, screen: (u32, u32)) -> Replay {
+        return Replay {
+            seed: seed,
+            timestep: timestep,
+            screen: screen,
+            length: 0,
+            frames: BTreeMap::new(),
+        };
+    }
+
+    pub fn frame(&self, frame: u64) -> Option<&ReplayFrame> {
+        return self.frames.get(&frame);
+    }
+
+    pub fn record(&mut self, frame: u64, events: Vec<ReplayEvent>, hash: Option<u64>) {
+        self.length = self.length.max(frame + 1);
+        if events.is_empty() && hash.is_none() {
+            return;
+        }
+
+        self.frames.insert(frame, ReplayFrame { events, hash });
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay, EngineError> {
+        return Replay::read(BufReader::new(File::open(path)?));
+    }
+
+    fn read<R: BufRead>(reader: R) -> Result<Replay, EngineError> {
+        let mut replay = Replay::new(0, Duration::from_millis(0), (0, 0));
+        let mut length = 0;
+
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line_number = index + 1;
+            let error = |message: &str| EngineError::Replay {
+                line: line_number,
+                message: message.to_string(),
+            };
+
+            if line_number == 1 {
+                if line.trim() != HEADER {
+                    return Err(error("not a replay file"));
+                }
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let first = match words.next() {
+                Some(first) => first,
+                None => continue,
+            };
+
+            match first {
+                "seed" => replay.seed = parse(&mut words).ok_or_else(|| error("bad seed"))?,
+                "timestep" => {
+                    let nanos: u64 = parse(&mut words).ok_or_else(|| error("bad timestep"))?;
+                    replay.timestep =
+                        Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32);
+                }
+                "screen" => {
+                    replay.screen = (
+                        parse(&mut words).ok_or_else(|| error("bad screen width"))?,
+                        parse(&mut words).ok_or_else(|| error("bad screen height"))?,
+                    );
+                }
+                "length" => length = parse(&mut words).ok_or_else(|| error("bad length"))?,
+                frame => {
+                    let frame: u64 = frame.parse().map_err(|_| error("unknown line"))?;
+                    let kind = words.next().unwrap_or("");
+                    let entry = replay.frames.entry(frame).or_insert(ReplayFrame {
+                        events: Vec::new(),
+                        hash: None,
+                    });
+
+                    if kind == "hash" {
+                        let hash = words
+                            .next()
+                            .and_then(|hash| u64::from_str_radix(hash, 16).ok());
+                        entry.hash = Some(hash.ok_or_else(|| error("bad hash"))?);
+                    } else {
+                        let event =
+                            parse_event(kind, &mut words).ok_or_else(|| error("bad event"))?;
+                        entry.events.push(event);
+                    }
+                }
+            }
+        }
+
+        let last_frame = replay.frames.keys().next_back().map(|frame| frame + 1);
+        replay.length = length.max(last_frame.unwrap_or(0));
+        return Ok(replay);
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), EngineError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()?;
+        return Ok(());
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<(), EngineError> {
+        let timestep =
+            self.timestep.as_secs() * 1_000_000_000 + self.timestep.subsec_nanos() as u64;
+
+        writeln!(writer, "{}", HEADER)?;
+        writeln!(writer, "seed {}", self.seed)?;
+        writeln!(writer, "timestep {}", timestep)?;
+        writeln!(writer, "screen {} {}", self.screen.0, self.screen.1)?;
+        writeln!(writer, "length {}", self.length)?;
+
+        for (frame, entry) in &self.frames {
+            for event in &entry.events {
+                writeln!(writer, "{} {}", frame, format_event(event))?;
+            }
+
+            if let Some(hash) = entry.hash {
+                writeln!(writer, "{} hash {:016x}", frame, hash)?;
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+/// A hash of everything the simulation depends on: game time, the RNG and the state of
+/// every entity. Two runs that agree on it each frame have played out the same way.
+pub fn world_hash(context: &Context) -> u64 {
+    let mut hasher = Fnv::new();
+
+    let game_time = context.clock.game_time();
+    hasher.write(&game_time.as_secs().to_le_bytes());
+    hasher.write(&game_time.subsec_nanos().to_le_bytes());
+
+    let random = context.random.save_state();
+    hasher.write(&random.seed.to_le_bytes());
+    for (name, state) in random.streams {
+        hasher.write(name.as_bytes());
+        hasher.write(&state.to_le_bytes());
+    }
+
+    let mut buffer = Vec::new();
+    for index in 0..context.entities.raw_len() {
+        if let Some(entity) = context.entities.get_raw_ref(index) {
+            buffer.clear();
+            entity.save_state(&mut buffer);
+            hasher.write(&(index as u64).to_le_bytes());
+            hasher.write(&buffer);
+        }
+    }
+
+    return hasher.finish();
+}
+
+fn format_event(event: &ReplayEvent) -> String {
+    return match *event {
+        ReplayEvent::Input(InputEvent::KeyPressed(KeyCode(key))) => format!("key_pressed {}", key),
+        ReplayEvent::Input(InputEvent::KeyReleased(KeyCode(key))) => {
+            format!("key_released {}", key)
+        }
+        ReplayEvent::Input(InputEvent::MouseMoved { x, y }) => format!("mouse_moved {} {}", x, y),
+        ReplayEvent::Input(InputEvent::MousePressed(button)) => {
+            format!("mouse_pressed {}", format_button(button))
+        }
+        ReplayEvent::Input(InputEvent::MouseReleased(button)) => {
+            format!("mouse_released {}", format_button(button))
+        }
+        ReplayEvent::Input(InputEvent::MouseWheel { dx, dy }) => {
+            format!("mouse_wheel {} {}", dx, dy)
+        }
+        ReplayEvent::Resize(width, height) => format!("resize {} {}", width, height),
+    };
+}
+
+fn parse_event(kind: &str, words: &mut SplitWhitespace) -> Option<ReplayEvent> {
+    let event = match kind {
+        "key_pressed" => InputEvent::KeyPressed(KeyCode(parse(words)?)),
+        "key_released" => InputEvent::KeyReleased(KeyCode(parse(words)?)),
+        "mouse_moved" => InputEvent::MouseMoved {
+            x: parse(words)?,
+            y: parse(words)?,
+        },
+        "mouse_pressed" => InputEvent::MousePressed(parse_button(words.next()?)?),
+        "mouse_released" => InputEvent::MouseReleased(parse_button(words.next()?)?),
+        "mouse_wheel" => InputEvent::MouseWheel {
+            dx: parse(words)?,
+            dy: parse(words)?,
+        },
+        "resize" => return Some(ReplayEvent::Resize(parse(words)?, parse(words)?)),
+        _ => return None,
+    };
+
+    return Some(ReplayEvent::Input(event));
+}
+
+fn format_button(button: MouseButton) -> String {
+    return match button {
+        MouseButton::Left => "left".to_string(),
+        MouseButton::Right => "right".to_string(),
+        MouseButton::Middle => "middle".to_string(),
+        MouseButton::Other(index) => index.to_string(),
+    };
+}
+
+fn parse_button(word: &str) -> Option<MouseButton> {
+    return match word {
+        "left" => Some(MouseButton::Left),
+        "right" => Some(MouseButton::Right),
+        "middle" => Some(MouseButton::Middle),
+        index => index.parse().ok().map(MouseButton::Other),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::lines;
+
+    fn error_line(text: &str) -> Option<(usize, String)> {
+        return lines::error_line(Replay::read(text.as_bytes()));
+    }
+
+    #[test]
+    fn saved_replays_read_back() {
+        let mut replay = Replay::new(1234, Duration::new(0, 16_666_667), (1280, 720));
+        let events = vec![
+            ReplayEvent::Input(InputEvent::KeyPressed(KeyCode(57))),
+            ReplayEvent::Input(InputEvent::MouseMoved { x: 640.5, y: 360.0 }),
+            ReplayEvent::Input(InputEvent::MousePressed(MouseButton::Other(4))),
+            ReplayEvent::Resize(800, 600),
+        ];
+        replay.record(12, events.clone(), Some(0x9c3ae0e2d1f3a4b5));
+        replay.record(599, Vec::new(), None);
+
+        let mut text = Vec::new();
+        replay.write(&mut text).unwrap();
+        let read = Replay::read(&text[..]).unwrap();
+
+        assert_eq!(read.seed, 1234);
+        assert_eq!(read.timestep, Duration::new(0, 16_666_667));
+        assert_eq!(read.screen, (1280, 720));
+        assert_eq!(read.length, 600);
+        assert_eq!(read.frame(12).unwrap().events, events);
+        assert_eq!(read.frame(12).unwrap().hash, Some(0x9c3ae0e2d1f3a4b5));
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        assert_eq!(
+            error_line("violetta-clip 1\n"),
+            Some((1, "not a replay file".to_string()))
+        );
+        assert_eq!(
+            error_line("violetta-replay 1\nseed 12\n\nseed twelve\n"),
+            Some((4, "bad seed".to_string()))
+        );
+        assert_eq!(
+            error_line("violetta-replay 1\nscreen 1280\n"),
+            Some((2, "bad screen height".to_string()))
+        );
+        assert_eq!(
+            error_line("violetta-replay 1\nspeed 2\n"),
+            Some((2, "unknown line".to_string()))
+        );
+        assert_eq!(
+            error_line("violetta-replay 1\n3 key_pressed 1\n3 jump\n"),
+            Some((3, "bad event".to_string()))
+        );
+        assert_eq!(
+            error_line("violetta-replay 1\n3 hash xyz\n"),
+            Some((2, "bad hash".to_string()))
+        );
+    }
+}
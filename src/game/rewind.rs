@@ -0,0 +1,237 @@
+use components::animator::Animator;
+use components::camera::CameraView;
+use components::renderable::Renderable;
+use components::Component;
+use context::generational::Layout;
+use context::random::RandomState;
+use context::Context;
+use entities::Entity;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+/// Creates an empty component for `load_state` to fill in, used when an entity has to be
+/// rebuilt from a snapshot
+pub type ComponentFactory = fn() -> Box<Component>;
+
+#[derive(Debug, Clone, PartialEq)]
+struct EntityState {
+    id: u64,
+    components: Vec<(&'static str, Vec<u8>)>,
+}
+
+// The entities of the world at one frame
+#[derive(Clone)]
+struct World {
+    layout: Layout,
+    entities: Vec<Option<EntityState>>,
+}
+
+impl World {
+    fn new() -> World {
+        return World {
+            layout: Layout::new(),
+            entities: Vec::new(),
+        };
+    }
+
+    fn capture(context: &Context) -> World {
+        let entities = (0..context.entities.raw_len())
+            .map(|index| {
+                return context
+                    .entities
+                    .get_raw_ref(index)
+                    .map(|entity| EntityState {
+                        id: entity.id(),
+                        components: entity.component_states(),
+                    });
+            })
+            .collect();
+
+        return World {
+            layout: context.entities.layout(),
+            entities: entities,
+        };
+    }
+
+    fn apply(&mut self, frame: &Frame) {
+        if let Some(ref layout) = frame.layout {
+            self.layout = layout.clone();
+            self.entities.resize(layout.len(), None);
+        }
+
+        for &(index, ref entity) in &frame.entities {
+            self.entities[index] = entity.clone();
+        }
+    }
+}
+
+// What changed during a frame: only the entities whose state differs from the frame
+// before, and the layout if entities were added or removed
+struct Frame {
+    frame: u64,
+    game_time: Duration,
+    random: RandomState,
+    layout: Option<Layout>,
+    entities: Vec<(usize, Option<EntityState>)>,
+}
+
+/// Keeps the last `capacity` frames of the world so they can be scrubbed through and played
+/// on from. Game state kept outside the context isn't covered.
+pub struct Rewind {
+    capacity: usize,
+    factories: HashMap<&'static str, ComponentFactory>,
+    frames: VecDeque<Frame>,
+    // The world before the oldest frame and after the newest one
+    base: World,
+    latest: World,
+    cursor: Option<usize>,
+}
+
+impl Rewind {
+    /// Knows how to rebuild the engine's own components. Cameras keep no state, so rebuilt
+    /// ones start out as `CameraView::new()` makes them.
+    pub fn new(capacity: usize) -> Rewind {
+        let mut rewind = Rewind {
+            capacity: capacity.max(1),
+            factories: HashMap::new(),
+            frames: VecDeque::new(),
+            base: World::new(),
+            latest: World::new(),
+            cursor: None,
+        };
+        rewind.register_component(Renderable::name(), || Renderable::new() as Box<Component>);
+        rewind.register_component(Animator::name(), || Animator::new() as Box<Component>);
+        rewind.register_component(CameraView::name(), || CameraView::new() as Box<Component>);
+        return rewind;
+    }
+
+    pub fn register_component(&mut self, name: &'static str, factory: ComponentFactory) {
+        self.factories.insert(name, factory);
+    }
+
+    /// Stores the world as it is at the end of the frame
+    pub fn capture(&mut self, frame: u64, context: &Context) {
+        let world = World::capture(context);
+        let layout = if world.layout != self.latest.layout {
+            Some(world.layout.clone())
+        } else {
+            None
+        };
+
+        let mut entities = Vec::new();
+        for (index, entity) in world.entities.iter().enumerate() {
+            if self.latest.entities.get(index) != Some(entity) {
+                entities.push((index, entity.clone()));
+            }
+        }
+
+        self.frames.push_back(Frame {
+            frame: frame,
+            game_time: context.clock.game_time(),
+            random: context.random.save_state(),
+            layout: layout,
+            entities: entities,
+        });
+        self.latest = world;
+
+        while self.frames.len() > self.capacity {
+            if let Some(oldest) = self.frames.pop_front() {
+                self.base.apply(&oldest);
+            }
+        }
+    }
+
+    pub fn is_scrubbing(&self) -> bool {
+        return self.cursor.is_some();
+    }
+
+    /// Starts scrubbing at the newest frame, returning its number
+    pub fn begin_scrub(&mut self) -> Option<u64> {
+        if self.frames.is_empty() {
+            return None;
+        }
+
+        let index = self.frames.len() - 1;
+        self.cursor = Some(index);
+        return Some(self.frames[index].frame);
+    }
+
+    /// Moves the given number of frames back (negative) or forward and restores the world
+    /// there, returning the frame number
+    pub fn step(&mut self, frames: isize, context: &mut Context) -> Option<u64> {
+        let cursor = match self.cursor {
+            Some(cursor) => cursor as isize,
+            None => return None,
+        };
+
+        let last = self.frames.len() as isize - 1;
+        let index = (cursor + frames).max(0).min(last) as usize;
+        if index != cursor as usize {
+            self.cursor = Some(index);
+            self.restore(index, context);
+        }
+
+        return Some(self.frames[index].frame);
+    }
+
+    /// Continues from the frame being scrubbed, dropping every frame after it
+    pub fn resume(&mut self) -> Option<u64> {
+        let index = match self.cursor.take() {
+            Some(index) => index,
+            None => return None,
+        };
+
+        self.frames.truncate(index + 1);
+        self.latest = self.world_at(index);
+        return Some(self.frames[index].frame);
+    }
+
+    fn world_at(&self, index: usize) -> World {
+        let mut world = self.base.clone();
+        for frame in self.frames.iter().take(index + 1) {
+            world.apply(frame);
+        }
+
+        return world;
+    }
+
+    fn restore(&self, index: usize, context: &mut Context) {
+        let world = self.world_at(index);
+        context.entities.restore_layout(&world.layout);
+
+        for (index, state) in world.entities.iter().enumerate() {
+            let state = match *state {
+                Some(ref state) => state,
+                None => {
+                    context.entities.set_raw(index, None);
+                    continue;
+                }
+            };
+
+            // Components are loaded in place where possible so systems keep their
+            // resources, otherwise the entity is built again
+            if let Some(entity) = context.entities.get_raw(index) {
+                if entity.id() == state.id && entity.load_component_states(&state.components) {
+                    continue;
+                }
+            }
+
+            let mut entity = Entity::new(state.id);
+            for &(name, ref data) in &state.components {
+                match self.factories.get(name) {
+                    Some(factory) => {
+                        let mut component = factory();
+                        component.load_state(data);
+                        entity.insert_component(component);
+                    }
+                    None => vlog_warn!("Cannot rewind component {} without a factory", name),
+                }
+            }
+            context.entities.set_raw(index, Some(Box::new(entity)));
+        }
+
+        let frame = &self.frames[index];
+        context.clock.set_game_time(frame.game_time);
+        context.random.restore_state(&frame.random);
+    }
+}
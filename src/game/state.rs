@@ -0,0 +1,284 @@
+use components::renderable::Renderable;
+use context::generational::Key;
+use context::tasks::TaskScope;
+use context::Context;
+use error::EngineError;
+use game::Game;
+
+pub enum Transition {
+    None,
+    Push(Box<State>),
+    Pop,
+    Switch(Box<State>),
+}
+
+/// A screen of the game such as a menu, gameplay or a pause overlay. Entities a state adds
+/// with `Context::spawn` belong to it and are removed when it exits, and tasks it starts
+/// are cancelled.
+pub trait State {
+    fn update(&mut self, context: &mut Context) -> Transition;
+
+    fn on_enter(&mut self, _context: &mut Context) {}
+    fn on_exit(&mut self, _context: &mut Context) {}
+
+    /// Called when another state is pushed on top of this one, and when it is popped again
+    fn on_covered(&mut self, _context: &mut Context) {}
+    fn on_uncovered(&mut self, _context: &mut Context) {}
+
+    /// Overlays let the states below them keep rendering underneath
+    fn is_overlay(&self) -> bool {
+        return false;
+    }
+}
+
+struct Entry {
+    state: Box<State>,
+    entities: Vec<Key>,
+    // The entities the stack hid because the state was covered. Entities the state hid
+    // itself are left alone.
+    hidden: Vec<Key>,
+    tasks: TaskScope,
+}
+
+/// Runs the topmost of a stack of states. The game exits once the stack is empty.
+pub struct StateStack {
+    initial: Option<Box<State>>,
+    entries: Vec<Entry>,
+}
+
+impl StateStack {
+    pub fn new(initial: Box<State>) -> StateStack {
+        return StateStack {
+            initial: Some(initial),
+            entries: Vec::new(),
+        };
+    }
+
+    fn apply(&mut self, context: &mut Context, transition: Transition) {
+        match transition {
+            Transition::None => return,
+            Transition::Push(state) => self.push(context, state),
+            Transition::Pop => self.pop(context),
+            Transition::Switch(state) => {
+                self.pop_entry(context);
+                self.push_entry(context, state);
+            }
+        }
+
+        self.update_visibility(context);
+    }
+
+    fn push(&mut self, context: &mut Context, state: Box<State>) {
+        if let Some(entry) = self.entries.last_mut() {
+            context.begin_entity_scope();
+            context.tasks.set_scope(Some(entry.tasks));
+            entry.state.on_covered(context);
+            context.tasks.set_scope(None);
+            entry.entities.extend(context.end_entity_scope());
+        }
+
+        self.push_entry(context, state);
+    }
+
+    fn pop(&mut self, context: &mut Context) {
+        self.pop_entry(context);
+
+        if let Some(entry) = self.entries.last_mut() {
+            context.begin_entity_scope();
+            context.tasks.set_scope(Some(entry.tasks));
+            entry.state.on_uncovered(context);
+            context.tasks.set_scope(None);
+            entry.entities.extend(context.end_entity_scope());
+        }
+    }
+
+    fn push_entry(&mut self, context: &mut Context, mut state: Box<State>) {
+        let tasks = context.tasks.new_scope();
+        context.begin_entity_scope();
+        context.tasks.set_scope(Some(tasks));
+        state.on_enter(context);
+        context.tasks.set_scope(None);
+        let entities = context.end_entity_scope();
+
+        self.entries.push(Entry {
+            state,
+            entities,
+            hidden: Vec::new(),
+            tasks,
+        });
+    }
+
+    fn pop_entry(&mut self, context: &mut Context) {
+        if let Some(mut entry) = self.entries.pop() {
+            context.begin_entity_scope();
+            context.tasks.set_scope(Some(entry.tasks));
+            entry.state.on_exit(context);
+            context.tasks.set_scope(None);
+            entry.entities.extend(context.end_entity_scope());
+
+            for key in &entry.entities {
+                context.entities.remove(key);
+            }
+            context.tasks.cancel_scope(entry.tasks);
+        }
+    }
+
+    // Shows the entities of the top state and of every state visible through overlays, and
+    // hides the rest
+    fn update_visibility(&mut self, context: &mut Context) {
+        let mut visible = true;
+        for entry in self.entries.iter_mut().rev() {
+            if visible {
+                for key in entry.hidden.drain(..) {
+                    if let Some(renderable) = renderable(context, &key) {
+                        renderable.set_visible(true);
+                    }
+                }
+            } else {
+                for key in &entry.entities {
+                    if let Some(renderable) = renderable(context, key) {
+                        if renderable.visible {
+                            renderable.set_visible(false);
+                            entry.hidden.push(*key);
+                        }
+                    }
+                }
+            }
+
+            visible = visible && entry.state.is_overlay();
+        }
+    }
+}
+
+fn renderable<'a>(context: &'a mut Context, key: &Key) -> Option<&'a mut Renderable> {
+    return context
+        .entities
+        .get(key)
+        .and_then(|entity| entity.get_component::<Renderable>(Renderable::name()));
+}
+
+impl Game for StateStack {
+    fn initialize(&mut self, context: &mut Context) -> Result<(), EngineError> {
+        if let Some(initial) = self.initial.take() {
+            self.push_entry(context, initial);
+        }
+
+        return Ok(());
+    }
+
+    fn game_loop(&mut self, context: &mut Context) {
+        let transition = match self.entries.last_mut() {
+            Some(entry) => {
+                context.begin_entity_scope();
+                context.tasks.set_scope(Some(entry.tasks));
+                let transition = entry.state.update(context);
+                context.tasks.set_scope(None);
+
+                // Forget entities the state has removed itself
+                entry
+                    .entities
+                    .retain(|key| context.entities.get(key).is_some());
+                entry.entities.extend(context.end_entity_scope());
+                transition
+            }
+            None => Transition::None,
+        };
+
+        self.apply(context, transition);
+
+        if self.entries.is_empty() {
+            context.request_exit();
+        }
+    }
+
+    fn on_exit(&mut self, context: &mut Context) {
+        while !self.entries.is_empty() {
+            self.pop_entry(context);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use context::tasks::{next_frame, Step};
+    use entities::Entity;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // Spawns a shown and a hidden entity when entered, and a leftover when exited
+    struct Level {
+        spawned: Rc<RefCell<Vec<Key>>>,
+    }
+
+    impl State for Level {
+        fn update(&mut self, _context: &mut Context) -> Transition {
+            return Transition::None;
+        }
+
+        fn on_enter(&mut self, context: &mut Context) {
+            for &visible in &[true, false] {
+                let mut entity = Box::new(Entity::new(0));
+                let mut renderable = Renderable::new();
+                renderable.set_visible(visible);
+                entity.set_component(renderable);
+                self.spawned.borrow_mut().push(context.spawn(entity));
+            }
+        }
+
+        fn on_exit(&mut self, context: &mut Context) {
+            let key = context.spawn(Box::new(Entity::new(0)));
+            self.spawned.borrow_mut().push(key);
+            context
+                .tasks
+                .spawn(|_: &mut Context| -> Step { next_frame() });
+        }
+    }
+
+    struct Pause {}
+
+    impl State for Pause {
+        fn update(&mut self, _context: &mut Context) -> Transition {
+            return Transition::None;
+        }
+    }
+
+    fn visible(context: &mut Context, key: &Key) -> bool {
+        return renderable(context, key).unwrap().visible;
+    }
+
+    #[test]
+    fn uncovering_keeps_entities_the_state_hid() {
+        let mut context = Context::new();
+        let spawned = Rc::new(RefCell::new(Vec::new()));
+        let mut stack = StateStack::new(Box::new(Level {
+            spawned: spawned.clone(),
+        }));
+        stack.initialize(&mut context).unwrap();
+        let (shown, hidden) = (spawned.borrow()[0], spawned.borrow()[1]);
+
+        stack.apply(&mut context, Transition::Push(Box::new(Pause {})));
+        assert!(!visible(&mut context, &shown));
+        assert!(!visible(&mut context, &hidden));
+
+        stack.apply(&mut context, Transition::Pop);
+        assert!(visible(&mut context, &shown));
+        assert!(!visible(&mut context, &hidden));
+    }
+
+    #[test]
+    fn what_on_exit_starts_is_cleaned_up() {
+        let mut context = Context::new();
+        let spawned = Rc::new(RefCell::new(Vec::new()));
+        let mut stack = StateStack::new(Box::new(Level {
+            spawned: spawned.clone(),
+        }));
+        stack.initialize(&mut context).unwrap();
+
+        stack.apply(&mut context, Transition::Pop);
+        for key in spawned.borrow().iter() {
+            assert!(!context.entities.contains(key));
+        }
+        assert_eq!(context.tasks.len(), 0);
+    }
+}
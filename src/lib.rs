@@ -0,0 +1,26 @@
+//! Violetta is a small 2D game engine built on OpenGL.
+//!
+//! A game implements [`game::Game`] and hands it to a [`game::GameRunner`], which owns the
+//! window and runs the frame loop. Every frame the game loop gets a [`context::Context`]
+//! holding the world's [`entities`], the clock, input, randomness and the camera, while
+//! [`systems`] such as the renderer do their part before or after it. Engine features come
+//! as [`game::plugin::Plugin`]s so they can be swapped out.
+//!
+//! Rendering can be left out by turning off the default `rendering` feature, in which case
+//! games run headless. glutin is still a dependency then, as input and errors use its
+//! types.
+
+#[cfg(feature = "rendering")]
+extern crate gl;
+extern crate glutin;
+extern crate rand;
+
+#[macro_use]
+pub mod util;
+
+pub mod components;
+pub mod context;
+pub mod entities;
+pub mod error;
+pub mod game;
+pub mod systems;
@@ -0,0 +1,258 @@
+use components::animator::{Animator, Playback};
+use context::animation::{Animations, SELF_PATH};
+use context::clock::TimeDomain;
+use context::Context;
+use error::EngineError;
+use game::plugin::Plugin;
+use game::GameRunner;
+use systems::Stage;
+
+/// Advances every `Animator` by the time of its domain and writes its clips' values to the entity and
+/// its children
+pub struct System {}
+
+impl System {
+    pub fn new() -> System {
+        return System {};
+    }
+}
+
+impl ::systems::System for System {
+    fn name(&self) -> &'static str {
+        return "animation";
+    }
+
+    fn initialize(&mut self) -> Result<(), EngineError> {
+        return Ok(());
+    }
+
+    fn apply(&mut self, context: &mut Context) {
+        // The clocks can't be borrowed along with the entities, so their deltas are read first
+        let real_delta = context.clock_for(TimeDomain::Real).delta();
+        let ui_delta = context.clock_for(TimeDomain::Ui).delta();
+        let game_delta = context.clock_for(TimeDomain::Game).delta();
+        let animations = &context.animations;
+        let entities = &mut context.entities;
+
+        for index in 0..entities.raw_len() {
+            let (current, blended, weight, children, bases) = {
+                let animator = match entities
+                    .get_raw(index)
+                    .and_then(|entity| entity.get_component::<Animator>(Animator::name()))
+                {
+                    Some(animator) => animator,
+                    None => continue,
+                };
+
+                let delta = match animator.domain() {
+                    TimeDomain::Real => real_delta,
+                    TimeDomain::Ui => ui_delta,
+                    TimeDomain::Game => game_delta,
+                };
+                animator.advance(delta, |name| animations.clip(name).map(|clip| clip.length));
+                (
+                    animator.current().cloned(),
+                    animator.blended().cloned(),
+                    animator.blend_weight(),
+                    animator.children().to_vec(),
+                    animator.blend_bases().to_vec(),
+                )
+            };
+
+            let values = blend(animations, current, blended, weight);
+            let mut new_bases = Vec::new();
+            for (path, field, value, strength) in values {
+                let entity = if path == SELF_PATH {
+                    entities.get_raw(index)
+                } else {
+                    match children.iter().find(|&&(ref name, _)| *name == path) {
+                        Some(&(_, key)) if entities.contains(&key) => entities.get(&key),
+                        _ => None,
+                    }
+                };
+
+                if let Some(entity) = entity {
+                    let value = match strength {
+                        Some(strength) => {
+                            let base = bases
+                                .iter()
+                                .find(|&&(ref base_path, ref base_field, _)| {
+                                    *base_path == path && *base_field == field
+                                })
+                                .map(|&(_, _, base)| base);
+                            let base = match base {
+                                Some(base) => base,
+                                None => match animations.read_field(entity, &field) {
+                                    Some(base) => {
+                                        new_bases.push((path.clone(), field.clone(), base));
+                                        base
+                                    }
+                                    None => continue,
+                                },
+                            };
+                            base + (value - base) * strength
+                        }
+                        None => value,
+                    };
+                    animations.write_field(entity, &field, value);
+                }
+            }
+
+            if let Some(animator) = entities
+                .get_raw(index)
+                .and_then(|entity| entity.get_component::<Animator>(Animator::name()))
+            {
+                for (path, field, base) in new_bases {
+                    animator.set_blend_base(&path, &field, base);
+                }
+            }
+        }
+    }
+
+    fn stage(&self) -> Stage {
+        return Stage::PreUpdate;
+    }
+}
+
+// Samples both clips and mixes tracks they share by the weight. Tracks only the current
+// clip has keep their values, while tracks only the blended clip has come with the weight
+// to mix them into what the field was before the blend.
+fn blend(
+    animations: &Animations,
+    current: Option<Playback>,
+    blended: Option<Playback>,
+    weight: f32,
+) -> Vec<(String, String, f32, Option<f32>)> {
+    let sample = |playback: Option<Playback>| -> Vec<(String, String, f32)> {
+        let playback = match playback {
+            Some(playback) => playback,
+            None => return Vec::new(),
+        };
+
+        return match animations.clip(&playback.clip) {
+            Some(clip) => clip
+                .sample(playback.time)
+                .into_iter()
+                .map(|(path, field, value)| (path.to_string(), field.to_string(), value))
+                .collect(),
+            None => Vec::new(),
+        };
+    };
+
+    let mut values: Vec<(String, String, f32, Option<f32>)> = sample(current)
+        .into_iter()
+        .map(|(path, field, value)| (path, field, value, None))
+        .collect();
+    for (path, field, value) in sample(blended) {
+        let shared = values
+            .iter_mut()
+            .find(|&&mut (ref other_path, ref other_field, _, _)| {
+                *other_path == path && *other_field == field
+            });
+        match shared {
+            Some(shared) => shared.2 += (value - shared.2) * weight,
+            None if weight > 0.0 => values.push((path, field, value, Some(weight))),
+            None => {}
+        }
+    }
+
+    return values;
+}
+
+/// Plays the clips of every `Animator` on its entity and the children bound to it
+pub struct AnimationPlugin {}
+
+impl Plugin for AnimationPlugin {
+    fn name(&self) -> &'static str {
+        return "animation";
+    }
+
+    fn build(&mut self, runner: &mut GameRunner) {
+        runner.register_system(Box::new(System::new()));
+    }
+
+    fn dependencies(&self) -> Vec<&'static str> {
+        return vec!["time"];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use components::renderable::Renderable;
+    use context::animation::{Clip, Interpolation, Keyframe, Track};
+    use entities::Entity;
+    use std::time::Duration;
+    use systems::System as SystemTrait;
+
+    #[test]
+    fn fixed_blends_do_not_build_up_over_frames() {
+        let mut context = Context::new();
+        let mut track = Track::new(SELF_PATH, "x");
+        track.add_keyframe(Keyframe {
+            time: 0.0,
+            value: 100.0,
+            interpolation: Interpolation::Linear,
+        });
+        let mut clip = Clip::new("slide");
+        clip.tracks.push(track);
+        context.animations.add_clip(clip);
+
+        let mut entity = Box::new(Entity::new(1));
+        entity.set_component(Renderable::new());
+        let mut animator = Animator::new();
+        animator.play("idle");
+        animator.blend("slide", 0.3);
+        entity.set_component(animator);
+        let key = context.spawn(entity);
+
+        let mut system = System::new();
+        for _ in 0..10 {
+            system.apply(&mut context);
+        }
+
+        let entity = context.entities.get(&key).unwrap();
+        let x = entity
+            .get_component::<Renderable>(Renderable::name())
+            .unwrap()
+            .x;
+        assert!((x - 30.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn animators_follow_their_domain() {
+        let mut context = Context::new();
+        let mut clip = Clip::new("spin");
+        clip.length = 1.0;
+        context.animations.add_clip(clip);
+        for &domain in &[TimeDomain::Ui, TimeDomain::Game] {
+            context
+                .clock_for_mut(domain)
+                .set_fixed_timestep(Some(Duration::from_millis(100)));
+        }
+        context.clock.pause();
+
+        let mut keys = Vec::new();
+        for &domain in &[TimeDomain::Ui, TimeDomain::Game] {
+            let mut entity = Box::new(Entity::new(keys.len() as u64));
+            let mut animator = Animator::new();
+            animator.play("spin");
+            animator.set_domain(domain);
+            entity.set_component(animator);
+            keys.push(context.spawn(entity));
+        }
+
+        context.ui_clock.tick();
+        context.clock.tick();
+        System::new().apply(&mut context);
+
+        let mut times = Vec::new();
+        for key in &keys {
+            let entity = context.entities.get(key).unwrap();
+            let animator = entity.get_component::<Animator>(Animator::name()).unwrap();
+            times.push(animator.current().unwrap().time);
+        }
+        assert!((times[0] - 0.1).abs() < 1e-6);
+        assert_eq!(times[1], 0.0);
+    }
+}
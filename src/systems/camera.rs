@@ -0,0 +1,97 @@
+use components::camera::CameraView;
+use components::renderable::Renderable;
+use context::follow::CameraFollow;
+use context::generational::{GenerationalArray, Key};
+use context::Context;
+use entities::Entity;
+use error::EngineError;
+use game::plugin::Plugin;
+use game::GameRunner;
+
+/// Fits camera entities to the window and moves cameras after their targets, once the
+/// game loop has moved them
+pub struct System {}
+
+impl System {
+    pub fn new() -> System {
+        return System {};
+    }
+}
+
+impl ::systems::System for System {
+    fn name(&self) -> &'static str {
+        return "camera";
+    }
+
+    fn initialize(&mut self) -> Result<(), EngineError> {
+        return Ok(());
+    }
+
+    fn apply(&mut self, context: &mut Context) {
+        let delta = context.clock.delta_seconds();
+
+        if let Some(follow) = context.resources.get_mut::<CameraFollow>() {
+            let entities = &mut context.entities;
+            let target = follow.target.and_then(|key| target_position(entities, key));
+            follow.update(&mut context.camera, target, delta);
+        }
+
+        let (screen_width, screen_height) = (context.screen.width, context.screen.height);
+        for index in 0..context.entities.raw_len() {
+            let target = match context
+                .entities
+                .get_raw(index)
+                .and_then(|entity| entity.get_component::<CameraView>(CameraView::name()))
+            {
+                Some(view) => {
+                    view.fit(screen_width, screen_height);
+                    match view.follow {
+                        Some(ref follow) => follow.target,
+                        None => continue,
+                    }
+                }
+                None => continue,
+            };
+
+            let target = target.and_then(|key| target_position(&mut context.entities, key));
+            if let Some(view) = context
+                .entities
+                .get_raw(index)
+                .and_then(|entity| entity.get_component::<CameraView>(CameraView::name()))
+            {
+                if let Some(ref mut follow) = view.follow {
+                    follow.update(&mut view.camera, target, delta);
+                }
+            }
+        }
+    }
+}
+
+fn target_position(entities: &mut GenerationalArray<Entity>, key: Key) -> Option<(f32, f32)> {
+    if !entities.contains(&key) {
+        return None;
+    }
+
+    return entities
+        .get(&key)
+        .and_then(|entity| entity.get_component::<Renderable>(Renderable::name()))
+        .map(|renderable| (renderable.x, renderable.y));
+}
+
+/// Fits camera entities to the window and moves cameras after their targets. The rendering
+/// plugin depends on it, so its system runs before anything is drawn.
+pub struct CameraPlugin {}
+
+impl Plugin for CameraPlugin {
+    fn name(&self) -> &'static str {
+        return "camera";
+    }
+
+    fn build(&mut self, runner: &mut GameRunner) {
+        runner.register_system(Box::new(System::new()));
+    }
+
+    fn dependencies(&self) -> Vec<&'static str> {
+        return vec!["time"];
+    }
+}
@@ -0,0 +1,53 @@
+use context::input::InputEvent;
+use context::Context;
+use error::EngineError;
+use game::plugin::Plugin;
+use game::GameRunner;
+use systems::Stage;
+
+/// Feeds the input events the game runner sent this frame into `context.input`
+pub struct System {
+    events: Vec<InputEvent>,
+}
+
+impl System {
+    pub fn new() -> System {
+        return System { events: Vec::new() };
+    }
+}
+
+impl ::systems::System for System {
+    fn name(&self) -> &'static str {
+        return "input";
+    }
+
+    fn initialize(&mut self) -> Result<(), EngineError> {
+        return Ok(());
+    }
+
+    fn apply(&mut self, context: &mut Context) {
+        self.events.clear();
+        if let Some(events) = context.events::<InputEvent>() {
+            self.events.extend_from_slice(events.this_frame());
+        }
+
+        context.input.begin_frame(&self.events);
+    }
+
+    fn stage(&self) -> Stage {
+        return Stage::PreUpdate;
+    }
+}
+
+pub struct InputPlugin {}
+
+impl Plugin for InputPlugin {
+    fn name(&self) -> &'static str {
+        return "input";
+    }
+
+    fn build(&mut self, runner: &mut GameRunner) {
+        runner.add_event::<InputEvent>();
+        runner.register_system(Box::new(System::new()));
+    }
+}
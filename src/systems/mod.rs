@@ -1,8 +1,41 @@
+//! Systems do engine work on the context every frame, before or after the game loop.
+
+pub mod animation;
+pub mod camera;
+pub mod input;
+#[cfg(feature = "rendering")]
 pub mod rendering;
+pub mod tasks;
+pub mod time;
+pub mod timers;
+pub mod tween;
 
 use context::Context;
+use error::EngineError;
+
+/// When in the frame a system runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Before the game loop, e.g. to bring time and input up to date
+    PreUpdate,
+    /// After the game loop, e.g. to draw what it did
+    PostUpdate,
+}
 
+/// Engine work done on the context once per frame
 pub trait System {
-    fn initialize(&mut self);
+    /// Identifies the system in profiling output, its type name unless overridden
+    fn name(&self) -> &'static str {
+        return ::std::any::type_name::<Self>();
+    }
+
+    fn initialize(&mut self) -> Result<(), EngineError>;
     fn apply(&mut self, context: &mut Context);
+
+    fn stage(&self) -> Stage {
+        return Stage::PostUpdate;
+    }
+
+    /// Called once when the game exits, before the GL context is destroyed
+    fn shutdown(&mut self) {}
 }
@@ -3,15 +3,20 @@ extern crate gl;
 mod texture;
 
 use self::texture::TextureManager;
+use components::camera::{self, CameraView, ALL_LAYERS};
 use components::renderable::{Renderable, VertexRange};
+use context::camera::Camera;
 use context::Context;
+use error::EngineError;
+use game::plugin::Plugin;
+use game::GameRunner;
 use gl::types::{GLboolean, GLfloat, GLsizei, GLsizeiptr, GLuint, GLvoid};
-use std::cmp;
 use std::collections::BTreeSet;
 use std::ffi::CString;
 use std::mem;
 use std::ptr;
 use util;
+use util::trace;
 
 static VS_SRC: &'static str = "
 #version 330 core
@@ -22,21 +27,30 @@ layout (location = 3) in vec3 aScale;
 
 uniform mat4 uView;
 uniform mat4 uProjection;
+uniform bool uSnap;
 
 out vec4 vertexColor;
 
 void main() {
     vec4 pos = vec4(aPos, 1.0);
-    
+
+    vec3 size = aScale;
+    vec3 position = aTranslate;
+    if (uSnap) {
+        // Keep every edge on a whole virtual pixel so sprites don't shimmer as they move
+        size.xy = floor(size.xy + 0.5);
+        position.xy = floor(position.xy - size.xy * 0.5 + 0.5) + size.xy * 0.5;
+    }
+
     mat4 scale = mat4(1.0);
-    scale[0][0] = aScale.x;
-    scale[1][1] = aScale.y;
-    scale[2][2] = aScale.z;
+    scale[0][0] = size.x;
+    scale[1][1] = size.y;
+    scale[2][2] = size.z;
 
     mat4 translate = mat4(1.0);
-    translate[3][0] = aTranslate.x;
-    translate[3][1] = aTranslate.y;
-    translate[3][2] = aTranslate.z;
+    translate[3][0] = position.x;
+    translate[3][1] = position.y;
+    translate[3][2] = position.z;
 
     mat4 model = translate * scale;
 
@@ -54,6 +68,29 @@ void main() {
     FragColor = vertexColor;
 }";
 
+// Copies a camera's offscreen target into its viewport
+static BLIT_VS_SRC: &'static str = "
+#version 330 core
+out vec2 uv;
+
+void main() {
+    // The corners of the viewport, drawn as a triangle strip
+    uv = vec2(float(gl_VertexID % 2), float(gl_VertexID / 2));
+    gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+}";
+
+static BLIT_FS_SRC: &'static str = "
+#version 330 core
+out vec4 FragColor;
+
+in vec2 uv;
+
+uniform sampler2D uTarget;
+
+void main() {
+    FragColor = texture(uTarget, uv);
+}";
+
 const VERTEX_SIZE: i32 = 12;
 const VERTS_PER_OBJECT: usize = 4;
 const INDICES_PER_OBJECT: usize = 6;
@@ -79,6 +116,11 @@ pub struct System {
     // Uniforms
     u_view: i32,
     u_projection: i32,
+    u_snap: i32,
+
+    // Offscreen targets for pixel perfect rendering, one per virtual resolution
+    targets: Vec<Target>,
+    blit_program: u32,
 
     // Textures
     texture_manager: TextureManager,
@@ -102,20 +144,53 @@ impl System {
 
             u_view: 0,
             u_projection: 0,
+            u_snap: 0,
+
+            targets: Vec::new(),
+            blit_program: 0,
 
             texture_manager: TextureManager::new(),
         };
     }
 }
 
+struct Target {
+    size: (u32, u32),
+    framebuffer: u32,
+    texture: u32,
+    // Whether a camera drew into it this frame. Targets no camera uses are deleted.
+    used: bool,
+}
+
+pub struct RenderingPlugin {}
+
+impl Plugin for RenderingPlugin {
+    fn name(&self) -> &'static str {
+        return "rendering";
+    }
+
+    fn build(&mut self, runner: &mut GameRunner) {
+        // There is no GL context to draw with
+        if runner.is_headless() {
+            return;
+        }
+
+        runner.register_system(Box::new(System::new()));
+    }
+
+    fn dependencies(&self) -> Vec<&'static str> {
+        return vec!["camera"];
+    }
+}
+
 impl ::systems::System for System {
-    fn initialize(&mut self) {
+    fn name(&self) -> &'static str {
+        return "rendering";
+    }
+
+    fn initialize(&mut self) -> Result<(), EngineError> {
         self.program = unsafe {
-            let vs = util::shader::compile_shader(VS_SRC, gl::VERTEX_SHADER);
-            let fs = util::shader::compile_shader(FS_SRC, gl::FRAGMENT_SHADER);
-            let program = util::shader::link_program(vs, fs);
-            gl::DeleteShader(vs);
-            gl::DeleteShader(fs);
+            let program = build_program(VS_SRC, FS_SRC)?;
 
             self.u_view = gl::GetUniformLocation(
                 program,
@@ -127,8 +202,24 @@ impl ::systems::System for System {
                 CString::new("uProjection").unwrap().as_ptr() as *const i8,
             );
 
+            self.u_snap = gl::GetUniformLocation(
+                program,
+                CString::new("uSnap").unwrap().as_ptr() as *const i8,
+            );
+
             program
         };
+        self.blit_program = match build_program(BLIT_VS_SRC, BLIT_FS_SRC) {
+            Ok(program) => program,
+            Err(error) => {
+                // Systems that fail to initialize aren't shut down, so clean up here
+                unsafe {
+                    gl::DeleteProgram(self.program);
+                }
+                self.program = 0;
+                return Err(error);
+            }
+        };
 
         unsafe {
             gl::GenVertexArrays(1, &mut self.vao);
@@ -152,10 +243,14 @@ impl ::systems::System for System {
                 gl::DYNAMIC_DRAW,
             );
         }
+
+        return Ok(());
     }
 
     fn apply(&mut self, context: &mut Context) {
         unsafe {
+            // Clear everything once, cameras then clear their own viewports
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
             gl::ClearColor(0.0, 0.0, 0.0, 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT);
 
@@ -163,46 +258,67 @@ impl ::systems::System for System {
             gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
             gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ebo);
             gl::UseProgram(self.program);
-
-            let view = &context.camera.view_matrix;
-            gl::UniformMatrix4fv(
-                self.u_view,
-                1,
-                gl::TRUE as GLboolean,
-                mem::transmute(&view[0]),
-            );
-
-            let projection = &context.camera.projection_matrix;
-            gl::UniformMatrix4fv(
-                self.u_projection,
-                1,
-                gl::TRUE as GLboolean,
-                mem::transmute(&projection[0]),
-            );
         }
 
+        let scope = trace::scope("texture_upload");
         self.update_textures();
+        drop(scope);
 
-        let mut max_index = 0;
+        let scope = trace::scope("entity_scan");
+        let mut renderable_indices = Vec::new();
+        let mut cameras = Vec::new();
         for current_index in 0..context.entities.raw_len() {
             let entity = match context.entities.get_raw(current_index) {
                 Some(entity) => entity,
                 None => continue,
             };
 
-            let renderable = match entity.get_component::<Renderable>(Renderable::name()) {
+            if entity
+                .get_component::<Renderable>(Renderable::name())
+                .is_some()
+            {
+                renderable_indices.push(current_index);
+            }
+
+            if let Some(view) = entity.get_component::<CameraView>(CameraView::name()) {
+                cameras.push((view.priority, current_index));
+            }
+        }
+        drop(scope);
+
+        let scope = trace::scope("buffer_upload");
+        let mut live_vertex_ranges = BTreeSet::new();
+        let mut live_index_ranges = BTreeSet::new();
+        let mut layers = Vec::new();
+        for current_index in renderable_indices {
+            let renderable = match context
+                .entities
+                .get_raw(current_index)
+                .and_then({ |entity| entity.get_component::<Renderable>(Renderable::name()) })
+            {
                 Some(renderable) => renderable,
                 None => continue,
             };
 
+            self.render(renderable);
+
+            if let Some(ref range) = renderable.vertex_range {
+                live_vertex_ranges.insert(range.start);
+            }
+
             match &renderable.index_range {
-                Some(range) => max_index = cmp::max(max_index, range.start + range.length),
+                Some(range) => {
+                    live_index_ranges.insert(range.start);
+                    layers.push((range.start, range.length, renderable.layer));
+                }
                 None => {}
             }
-
-            self.render(renderable);
         }
 
+        self.release_ranges(&live_vertex_ranges, &live_index_ranges);
+        layers.sort();
+        drop(scope);
+
         unsafe {
             gl::VertexAttribPointer(
                 0,
@@ -243,14 +359,66 @@ impl ::systems::System for System {
                 (9 * mem::size_of::<GLfloat>()) as *const GLvoid,
             );
             gl::EnableVertexAttribArray(3);
+        }
 
-            gl::DrawElements(
-                gl::TRIANGLES,
-                max_index as i32,
-                gl::UNSIGNED_INT,
-                ptr::null(),
-            );
+        let _scope = trace::scope("draw");
+        for target in self.targets.iter_mut() {
+            target.used = false;
+        }
+
+        if cameras.is_empty() {
+            let draws = visible_runs(&layers, ALL_LAYERS);
+            self.draw_camera(&context.camera, Some([0.0, 0.0, 0.0]), &draws);
         }
+
+        // Entity order breaks ties, so cameras with the same priority don't flicker
+        cameras.sort();
+        for (_, index) in cameras {
+            let view = match context
+                .entities
+                .get_raw(index)
+                .and_then(|entity| entity.get_component::<CameraView>(CameraView::name()))
+            {
+                Some(view) => view,
+                None => continue,
+            };
+
+            let draws = visible_runs(&layers, view.layer_mask);
+            self.draw_camera(&view.camera, view.clear_color, &draws);
+        }
+
+        self.delete_unused_targets();
+    }
+
+    fn shutdown(&mut self) {
+        for i in 0..self.texture_manager.num_groups() {
+            let group = self.texture_manager.get_group(i);
+            if group.handle != 0 {
+                unsafe {
+                    gl::DeleteTextures(1, &group.handle);
+                }
+                group.handle = 0;
+            }
+        }
+
+        for target in self.targets.iter_mut() {
+            target.used = false;
+        }
+        self.delete_unused_targets();
+
+        unsafe {
+            gl::DeleteBuffers(1, &self.ebo);
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteProgram(self.program);
+            gl::DeleteProgram(self.blit_program);
+        }
+
+        self.ebo = 0;
+        self.vbo = 0;
+        self.vao = 0;
+        self.program = 0;
+        self.blit_program = 0;
     }
 }
 
@@ -264,10 +432,17 @@ impl System {
             self.assign_vertex_range(renderable);
         }
 
+        // Hidden quads stay in the buffer, collapsed to nothing
+        let (width, height) = if renderable.visible {
+            (renderable.width, renderable.height)
+        } else {
+            (0.0, 0.0)
+        };
+
         unsafe {
             match renderable.vertex_range {
                 Some(ref range) => {
-                    let quad: [[GLfloat; VERTEX_SIZE as usize]; 4] = [
+                    let mut quad: [[GLfloat; VERTEX_SIZE as usize]; 4] = [
                         [
                             -0.5,
                             0.5,
@@ -278,8 +453,8 @@ impl System {
                             renderable.x,
                             renderable.y,
                             renderable.z,
-                            renderable.width,
-                            renderable.height,
+                            width,
+                            height,
                             0.0,
                         ], //
                         [
@@ -292,8 +467,8 @@ impl System {
                             renderable.x,
                             renderable.y,
                             renderable.z,
-                            renderable.width,
-                            renderable.height,
+                            width,
+                            height,
                             0.0,
                         ], //
                         [
@@ -306,8 +481,8 @@ impl System {
                             renderable.x,
                             renderable.y,
                             renderable.z,
-                            renderable.width,
-                            renderable.height,
+                            width,
+                            height,
                             0.0,
                         ], //
                         [
@@ -320,12 +495,19 @@ impl System {
                             renderable.x,
                             renderable.y,
                             renderable.z,
-                            renderable.width,
-                            renderable.height,
+                            width,
+                            height,
                             0.0,
                         ], //
                     ];
 
+                    // The corner colors are tinted by the renderable's color
+                    for vertex in quad.iter_mut() {
+                        for channel in 0..3 {
+                            vertex[3 + channel] *= renderable.color[channel];
+                        }
+                    }
+
                     for (i, vertex) in quad.iter().enumerate() {
                         let offset = (range.start + i) * VERTEX_SIZE as usize;
                         gl::BufferSubData(
@@ -377,6 +559,54 @@ impl System {
         self.index_ranges.insert(Box::new(index_range));
     }
 
+    // Frees the buffer ranges of renderables that no longer exist, clearing their data so
+    // stale quads aren't drawn
+    fn release_ranges(
+        &mut self,
+        live_vertex_ranges: &BTreeSet<usize>,
+        live_index_ranges: &BTreeSet<usize>,
+    ) {
+        let stale_vertex_ranges: Vec<Box<VertexRange>> = self
+            .vertex_ranges
+            .iter()
+            .filter(|range| !live_vertex_ranges.contains(&range.start))
+            .cloned()
+            .collect();
+
+        for range in stale_vertex_ranges {
+            let zeroes = vec![0.0 as GLfloat; range.length * VERTEX_SIZE as usize];
+            unsafe {
+                gl::BufferSubData(
+                    gl::ARRAY_BUFFER,
+                    (range.start * VERTEX_SIZE as usize * mem::size_of::<GLfloat>()) as GLsizeiptr,
+                    (zeroes.len() * mem::size_of::<GLfloat>()) as GLsizeiptr,
+                    mem::transmute(&zeroes[0]),
+                );
+            }
+            self.vertex_ranges.remove(&range);
+        }
+
+        let stale_index_ranges: Vec<Box<VertexRange>> = self
+            .index_ranges
+            .iter()
+            .filter(|range| !live_index_ranges.contains(&range.start))
+            .cloned()
+            .collect();
+
+        for range in stale_index_ranges {
+            let zeroes = vec![0 as GLuint; range.length];
+            unsafe {
+                gl::BufferSubData(
+                    gl::ELEMENT_ARRAY_BUFFER,
+                    (range.start * mem::size_of::<GLuint>()) as GLsizeiptr,
+                    (zeroes.len() * mem::size_of::<GLuint>()) as GLsizeiptr,
+                    mem::transmute(&zeroes[0]),
+                );
+            }
+            self.index_ranges.remove(&range);
+        }
+    }
+
     fn find_free_range(&self, ranges: &BTreeSet<Box<VertexRange>>, size: usize) -> VertexRange {
         let mut first_free_index = 0;
         for range in ranges.iter() {
@@ -392,6 +622,172 @@ impl System {
         };
     }
 
+    // Draws the index ranges, given as start and length, as seen by the camera
+    fn draw_camera(
+        &mut self,
+        camera: &Camera,
+        clear_color: Option<[f32; 3]>,
+        draws: &[(usize, usize)],
+    ) {
+        let target = match camera.virtual_resolution() {
+            Some(resolution) => self.bind_target(resolution),
+            None => None,
+        };
+
+        let viewport = &camera.viewport;
+        unsafe {
+            match target {
+                Some((_, (width, height))) => {
+                    // Without a clear color the target starts out transparent, so the
+                    // cameras below show through where nothing was drawn
+                    let [red, green, blue, alpha] = match clear_color {
+                        Some([red, green, blue]) => [red, green, blue, 1.0],
+                        None => [0.0, 0.0, 0.0, 0.0],
+                    };
+                    gl::Viewport(0, 0, width as i32, height as i32);
+                    gl::ClearColor(red, green, blue, alpha);
+                    gl::Clear(gl::COLOR_BUFFER_BIT);
+                }
+                None => {
+                    gl::Viewport(viewport.x, viewport.y, viewport.width, viewport.height);
+                    if let Some([red, green, blue]) = clear_color {
+                        // Clearing ignores the viewport, so it's limited with a scissor
+                        gl::Enable(gl::SCISSOR_TEST);
+                        gl::Scissor(viewport.x, viewport.y, viewport.width, viewport.height);
+                        gl::ClearColor(red, green, blue, 1.0);
+                        gl::Clear(gl::COLOR_BUFFER_BIT);
+                        gl::Disable(gl::SCISSOR_TEST);
+                    }
+                }
+            }
+
+            let view = &camera.view_matrix;
+            gl::UniformMatrix4fv(
+                self.u_view,
+                1,
+                gl::TRUE as GLboolean,
+                mem::transmute(&view[0]),
+            );
+
+            let projection = &camera.projection_matrix;
+            gl::UniformMatrix4fv(
+                self.u_projection,
+                1,
+                gl::TRUE as GLboolean,
+                mem::transmute(&projection[0]),
+            );
+
+            gl::Uniform1i(self.u_snap, target.is_some() as i32);
+
+            for &(start, length) in draws {
+                if length == 0 {
+                    continue;
+                }
+
+                gl::DrawElements(
+                    gl::TRIANGLES,
+                    length as i32,
+                    gl::UNSIGNED_INT,
+                    (start * mem::size_of::<GLuint>()) as *const GLvoid,
+                );
+            }
+        }
+
+        if let Some((texture, _)) = target {
+            // Upscaled with a quad rather than a blit, so transparent parts blend with what
+            // is already in the window
+            unsafe {
+                gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                gl::Viewport(viewport.x, viewport.y, viewport.width, viewport.height);
+                gl::UseProgram(self.blit_program);
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                gl::Enable(gl::BLEND);
+                gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+                gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+                gl::Disable(gl::BLEND);
+                gl::UseProgram(self.program);
+            }
+        }
+    }
+
+    // Binds the offscreen target of the given size, creating it if needed, and returns its
+    // texture and size. Returns None and leaves the window bound if the target can't be
+    // used.
+    fn bind_target(&mut self, size: (u32, u32)) -> Option<(u32, (u32, u32))> {
+        if let Some(target) = self.targets.iter_mut().find(|target| target.size == size) {
+            target.used = true;
+            unsafe {
+                gl::BindFramebuffer(gl::FRAMEBUFFER, target.framebuffer);
+            }
+            return Some((target.texture, size));
+        }
+
+        let mut target = Target {
+            size: size,
+            framebuffer: 0,
+            texture: 0,
+            used: true,
+        };
+
+        unsafe {
+            gl::GenTextures(1, &mut target.texture);
+            gl::BindTexture(gl::TEXTURE_2D, target.texture);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA8 as i32,
+                size.0 as i32,
+                size.1 as i32,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+
+            gl::GenFramebuffers(1, &mut target.framebuffer);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target.framebuffer);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                target.texture,
+                0,
+            );
+
+            if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
+                vlog_warn!("Offscreen framebuffer is incomplete, drawing to the window");
+                gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                gl::DeleteFramebuffers(1, &target.framebuffer);
+                gl::DeleteTextures(1, &target.texture);
+                return None;
+            }
+        }
+
+        let texture = target.texture;
+        self.targets.push(target);
+        return Some((texture, size));
+    }
+
+    fn delete_unused_targets(&mut self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+
+        self.targets.retain(|target| {
+            if target.used {
+                return true;
+            }
+
+            unsafe {
+                gl::DeleteFramebuffers(1, &target.framebuffer);
+                gl::DeleteTextures(1, &target.texture);
+            }
+            return false;
+        });
+    }
+
     fn update_textures(&mut self) {
         for i in 0..self.texture_manager.num_groups() {
             let group = self.texture_manager.get_group(i);
@@ -442,3 +838,43 @@ impl System {
         }
     }
 }
+
+// Groups the index ranges on layers in the mask into as few draws as possible. The
+// ranges are start, length and layer, sorted by start.
+fn visible_runs(layers: &[(usize, usize, u8)], layer_mask: u32) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    // Free ranges between them are zeroed and draw nothing, so only ranges on other layers
+    // split a run
+    let mut split = true;
+    for &(start, length, layer) in layers {
+        if !camera::mask_has_layer(layer_mask, layer) {
+            split = true;
+            continue;
+        }
+
+        match runs.last_mut() {
+            Some(run) if !split => run.1 = start + length - run.0,
+            _ => runs.push((start, length)),
+        }
+        split = false;
+    }
+
+    return runs;
+}
+
+fn build_program(vs_src: &str, fs_src: &str) -> Result<u32, EngineError> {
+    unsafe {
+        let vs = util::shader::compile_shader(vs_src, gl::VERTEX_SHADER)?;
+        let fs = match util::shader::compile_shader(fs_src, gl::FRAGMENT_SHADER) {
+            Ok(fs) => fs,
+            Err(err) => {
+                gl::DeleteShader(vs);
+                return Err(err);
+            }
+        };
+        let program = util::shader::link_program(vs, fs);
+        gl::DeleteShader(vs);
+        gl::DeleteShader(fs);
+        return program;
+    }
+}
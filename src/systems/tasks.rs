@@ -0,0 +1,51 @@
+use context::tasks;
+use context::Context;
+use error::EngineError;
+use game::plugin::Plugin;
+use game::GameRunner;
+use systems::Stage;
+
+/// Resumes tasks whose wait is over, once per frame before the game loop
+pub struct System {}
+
+impl System {
+    pub fn new() -> System {
+        return System {};
+    }
+}
+
+impl ::systems::System for System {
+    fn name(&self) -> &'static str {
+        return "tasks";
+    }
+
+    fn initialize(&mut self) -> Result<(), EngineError> {
+        return Ok(());
+    }
+
+    fn apply(&mut self, context: &mut Context) {
+        tasks::update(context);
+    }
+
+    fn stage(&self) -> Stage {
+        return Stage::PreUpdate;
+    }
+}
+
+/// Runs tasks after the clocks have ticked, input has been read and tweens have moved, so
+/// tasks waiting on any of them resume in the same frame
+pub struct TaskPlugin {}
+
+impl Plugin for TaskPlugin {
+    fn name(&self) -> &'static str {
+        return "tasks";
+    }
+
+    fn build(&mut self, runner: &mut GameRunner) {
+        runner.register_system(Box::new(System::new()));
+    }
+
+    fn dependencies(&self) -> Vec<&'static str> {
+        return vec!["time", "input", "tweens"];
+    }
+}
@@ -0,0 +1,46 @@
+use context::Context;
+use error::EngineError;
+use game::plugin::Plugin;
+use game::GameRunner;
+use systems::Stage;
+
+/// Advances the real, UI and game clocks at the start of every frame
+pub struct System {}
+
+impl System {
+    pub fn new() -> System {
+        return System {};
+    }
+}
+
+impl ::systems::System for System {
+    fn name(&self) -> &'static str {
+        return "time";
+    }
+
+    fn initialize(&mut self) -> Result<(), EngineError> {
+        return Ok(());
+    }
+
+    fn apply(&mut self, context: &mut Context) {
+        context.real_clock.tick();
+        context.ui_clock.tick();
+        context.clock.tick();
+    }
+
+    fn stage(&self) -> Stage {
+        return Stage::PreUpdate;
+    }
+}
+
+pub struct TimePlugin {}
+
+impl Plugin for TimePlugin {
+    fn name(&self) -> &'static str {
+        return "time";
+    }
+
+    fn build(&mut self, runner: &mut GameRunner) {
+        runner.register_system(Box::new(System::new()));
+    }
+}
@@ -0,0 +1,63 @@
+use context::timers::TimerFired;
+use context::Context;
+use error::EngineError;
+use game::plugin::Plugin;
+use game::GameRunner;
+use systems::Stage;
+
+/// Fires due timers, sending a `TimerFired` event and calling the timer's callback
+pub struct System {}
+
+impl System {
+    pub fn new() -> System {
+        return System {};
+    }
+}
+
+impl ::systems::System for System {
+    fn name(&self) -> &'static str {
+        return "timers";
+    }
+
+    fn initialize(&mut self) -> Result<(), EngineError> {
+        return Ok(());
+    }
+
+    fn apply(&mut self, context: &mut Context) {
+        let fired = context.timers.advance(
+            [&context.real_clock, &context.ui_clock, &context.clock],
+            &context.entities,
+        );
+
+        for event in fired {
+            context.send_event(event);
+            if let Some(mut callback) = context.timers.take_callback(event.handle) {
+                callback(context, event.handle);
+                context.timers.return_callback(event.handle, callback);
+            }
+        }
+    }
+
+    fn stage(&self) -> Stage {
+        return Stage::PreUpdate;
+    }
+}
+
+/// Fires the timers in `context.timers` and lets games read `TimerFired` events. Callbacks
+/// run after input has been read, so they see this frame's input.
+pub struct TimerPlugin {}
+
+impl Plugin for TimerPlugin {
+    fn name(&self) -> &'static str {
+        return "timers";
+    }
+
+    fn build(&mut self, runner: &mut GameRunner) {
+        runner.add_event::<TimerFired>();
+        runner.register_system(Box::new(System::new()));
+    }
+
+    fn dependencies(&self) -> Vec<&'static str> {
+        return vec!["time", "input"];
+    }
+}
@@ -0,0 +1,53 @@
+use context::tween::{self, TweenFinished};
+use context::Context;
+use error::EngineError;
+use game::plugin::Plugin;
+use game::GameRunner;
+use systems::Stage;
+
+/// Plays running tweens and sends a `TweenFinished` event for each one that ends
+pub struct System {}
+
+impl System {
+    pub fn new() -> System {
+        return System {};
+    }
+}
+
+impl ::systems::System for System {
+    fn name(&self) -> &'static str {
+        return "tweens";
+    }
+
+    fn initialize(&mut self) -> Result<(), EngineError> {
+        return Ok(());
+    }
+
+    fn apply(&mut self, context: &mut Context) {
+        for event in tween::update(context) {
+            context.send_event(event);
+        }
+    }
+
+    fn stage(&self) -> Stage {
+        return Stage::PreUpdate;
+    }
+}
+
+/// Plays the tweens in `context.tweens` and lets games read `TweenFinished` events
+pub struct TweenPlugin {}
+
+impl Plugin for TweenPlugin {
+    fn name(&self) -> &'static str {
+        return "tweens";
+    }
+
+    fn build(&mut self, runner: &mut GameRunner) {
+        runner.add_event::<TweenFinished>();
+        runner.register_system(Box::new(System::new()));
+    }
+
+    fn dependencies(&self) -> Vec<&'static str> {
+        return vec!["time"];
+    }
+}
@@ -0,0 +1,137 @@
+use std::f32::consts::PI;
+
+/// Easing curves, mapping linear progress from 0 to 1 onto eased progress. Most curves
+/// start at 0 and end at 1, `Back` and `Elastic` overshoot on the way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    QuartIn,
+    QuartOut,
+    QuartInOut,
+    QuintIn,
+    QuintOut,
+    QuintInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    CircIn,
+    CircOut,
+    CircInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+impl Easing {
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.max(0.0).min(1.0);
+        return match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t.powi(2),
+            Easing::QuadOut => ease_out(t, |t| t.powi(2)),
+            Easing::QuadInOut => ease_in_out(t, |t| t.powi(2)),
+            Easing::CubicIn => t.powi(3),
+            Easing::CubicOut => ease_out(t, |t| t.powi(3)),
+            Easing::CubicInOut => ease_in_out(t, |t| t.powi(3)),
+            Easing::QuartIn => t.powi(4),
+            Easing::QuartOut => ease_out(t, |t| t.powi(4)),
+            Easing::QuartInOut => ease_in_out(t, |t| t.powi(4)),
+            Easing::QuintIn => t.powi(5),
+            Easing::QuintOut => ease_out(t, |t| t.powi(5)),
+            Easing::QuintInOut => ease_in_out(t, |t| t.powi(5)),
+            Easing::SineIn => sine_in(t),
+            Easing::SineOut => ease_out(t, sine_in),
+            Easing::SineInOut => ease_in_out(t, sine_in),
+            Easing::ExpoIn => expo_in(t),
+            Easing::ExpoOut => ease_out(t, expo_in),
+            Easing::ExpoInOut => ease_in_out(t, expo_in),
+            Easing::CircIn => circ_in(t),
+            Easing::CircOut => ease_out(t, circ_in),
+            Easing::CircInOut => ease_in_out(t, circ_in),
+            Easing::BackIn => back_in(t),
+            Easing::BackOut => ease_out(t, back_in),
+            Easing::BackInOut => ease_in_out(t, back_in),
+            Easing::ElasticIn => elastic_in(t),
+            Easing::ElasticOut => ease_out(t, elastic_in),
+            Easing::ElasticInOut => ease_in_out(t, elastic_in),
+            Easing::BounceIn => ease_out(t, bounce_out),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => ease_in_out(t, |t| 1.0 - bounce_out(1.0 - t)),
+        };
+    }
+}
+
+// The out and in-out variants are built from the in curve
+fn ease_out<F: Fn(f32) -> f32>(t: f32, ease_in: F) -> f32 {
+    return 1.0 - ease_in(1.0 - t);
+}
+
+fn ease_in_out<F: Fn(f32) -> f32>(t: f32, ease_in: F) -> f32 {
+    if t < 0.5 {
+        return ease_in(t * 2.0) / 2.0;
+    }
+
+    return 1.0 - ease_in((1.0 - t) * 2.0) / 2.0;
+}
+
+fn sine_in(t: f32) -> f32 {
+    return 1.0 - (t * PI / 2.0).cos();
+}
+
+fn expo_in(t: f32) -> f32 {
+    if t == 0.0 {
+        return 0.0;
+    }
+
+    return 2.0f32.powf(10.0 * t - 10.0);
+}
+
+fn circ_in(t: f32) -> f32 {
+    return 1.0 - (1.0 - t * t).sqrt();
+}
+
+fn back_in(t: f32) -> f32 {
+    let overshoot = 1.70158;
+    return t * t * ((overshoot + 1.0) * t - overshoot);
+}
+
+fn elastic_in(t: f32) -> f32 {
+    if t == 0.0 || t == 1.0 {
+        return t;
+    }
+
+    let period = 2.0 * PI / 3.0;
+    return -(2.0f32.powf(10.0 * t - 10.0)) * ((t * 10.0 - 10.75) * period).sin();
+}
+
+fn bounce_out(t: f32) -> f32 {
+    let n = 7.5625;
+    let d = 2.75;
+    if t < 1.0 / d {
+        return n * t * t;
+    } else if t < 2.0 / d {
+        let t = t - 1.5 / d;
+        return n * t * t + 0.75;
+    } else if t < 2.5 / d {
+        let t = t - 2.25 / d;
+        return n * t * t + 0.9375;
+    }
+
+    let t = t - 2.625 / d;
+    return n * t * t + 0.984375;
+}
@@ -0,0 +1,28 @@
+use std::hash::Hasher;
+
+/// FNV-1a. Unlike the standard library's hasher it gives the same result on every run,
+/// so it can be used for seeds and for comparing state across runs.
+pub struct Fnv {
+    hash: u64,
+}
+
+impl Fnv {
+    pub fn new() -> Fnv {
+        return Fnv {
+            hash: 0xCBF2_9CE4_8422_2325,
+        };
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        return self.hash;
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(0x0000_0100_0000_01B3);
+        }
+    }
+}
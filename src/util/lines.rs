@@ -0,0 +1,21 @@
+use std::str::{FromStr, SplitWhitespace};
+
+#[cfg(test)]
+use error::EngineError;
+
+/// Parses the next word of a line, or returns None if the line has run out or the word
+/// doesn't parse. Used by the line-based formats of animation clips and replays.
+pub fn parse<T: FromStr>(words: &mut SplitWhitespace) -> Option<T> {
+    return words.next().and_then(|word| word.parse().ok());
+}
+
+/// The line and message of a parse error, for tests of the line-based formats
+#[cfg(test)]
+pub fn error_line<T>(result: Result<T, EngineError>) -> Option<(usize, String)> {
+    return match result {
+        Err(EngineError::Clip { line, message }) | Err(EngineError::Replay { line, message }) => {
+            Some((line, message))
+        }
+        _ => None,
+    };
+}
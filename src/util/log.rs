@@ -0,0 +1,407 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        };
+        return f.pad(name);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub level: Level,
+    pub module: String,
+    /// Time since the logger was first used
+    pub timestamp: Duration,
+    pub frame: u64,
+    pub message: String,
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(
+            f,
+            "[{:>4}.{:03}] [frame {}] {:<5} {}: {}",
+            self.timestamp.as_secs(),
+            self.timestamp.subsec_millis(),
+            self.frame,
+            self.level,
+            self.module,
+            self.message
+        );
+    }
+}
+
+pub trait Sink: Send {
+    fn write(&mut self, record: &Record);
+    fn flush(&mut self) {}
+}
+
+pub struct StderrSink;
+
+impl Sink for StderrSink {
+    fn write(&mut self, record: &Record) {
+        eprintln!("{}", record);
+    }
+}
+
+pub struct FileSink {
+    writer: BufWriter<File>,
+}
+
+impl FileSink {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<FileSink> {
+        return Ok(FileSink {
+            writer: BufWriter::new(File::create(path)?),
+        });
+    }
+}
+
+impl Sink for FileSink {
+    fn write(&mut self, record: &Record) {
+        // There's nowhere left to report a failing log file
+        let _ = writeln!(self.writer, "{}", record);
+    }
+
+    fn flush(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+/// Keeps the most recent records in memory, e.g. for an in-game console. The buffer is
+/// shared, so keep a clone of it around before handing the sink to `add_sink`.
+pub struct MemorySink {
+    buffer: LogBuffer,
+}
+
+#[derive(Clone)]
+pub struct LogBuffer {
+    records: Arc<Mutex<VecDeque<Record>>>,
+    capacity: usize,
+}
+
+impl MemorySink {
+    pub fn new(capacity: usize) -> MemorySink {
+        return MemorySink {
+            buffer: LogBuffer {
+                records: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+                capacity: capacity,
+            },
+        };
+    }
+
+    pub fn buffer(&self) -> LogBuffer {
+        return self.buffer.clone();
+    }
+}
+
+impl Sink for MemorySink {
+    fn write(&mut self, record: &Record) {
+        if let Ok(mut records) = self.buffer.records.lock() {
+            while records.len() >= self.buffer.capacity.max(1) {
+                records.pop_front();
+            }
+            records.push_back(record.clone());
+        }
+    }
+}
+
+impl LogBuffer {
+    /// Oldest record first
+    pub fn records(&self) -> Vec<Record> {
+        return match self.records.lock() {
+            Ok(records) => records.iter().cloned().collect(),
+            Err(_) => Vec::new(),
+        };
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut records) = self.records.lock() {
+            records.clear();
+        }
+    }
+}
+
+struct Logger {
+    epoch: Instant,
+    frame: u64,
+    level: Level,
+    // Module path prefixes with their own level. The longest matching prefix wins.
+    module_levels: Vec<(String, Level)>,
+    sinks: Vec<Box<Sink>>,
+}
+
+impl Logger {
+    fn new() -> Logger {
+        return Logger {
+            epoch: Instant::now(),
+            frame: 0,
+            level: Level::Info,
+            module_levels: Vec::new(),
+            sinks: vec![Box::new(StderrSink)],
+        };
+    }
+
+    fn enabled(&self, level: Level, module: &str) -> bool {
+        let max_level = self
+            .module_levels
+            .iter()
+            .filter(|&&(ref prefix, _)| is_module_prefix(prefix, module))
+            .max_by_key(|&&(ref prefix, _)| prefix.len())
+            .map(|&(_, level)| level)
+            .unwrap_or(self.level);
+
+        return level <= max_level;
+    }
+}
+
+static LOGGER: Mutex<Option<Logger>> = Mutex::new(None);
+
+thread_local! {
+    // Whether this thread holds the logger
+    static LOGGING: Cell<bool> = Cell::new(false);
+}
+
+// Lets the thread lock the logger again once it's done, even if a sink panicked
+struct LoggingGuard;
+
+impl Drop for LoggingGuard {
+    fn drop(&mut self) {
+        LOGGING.with(|logging| logging.set(false));
+    }
+}
+
+fn with_logger<F: FnOnce(&mut Logger)>(f: F) {
+    // Sinks that log would wait on the lock they hold forever, so whatever they log is
+    // dropped
+    if LOGGING.with(|logging| logging.replace(true)) {
+        return;
+    }
+    let _guard = LoggingGuard;
+
+    // A panic while logging shouldn't take logging down with it
+    let mut logger = match LOGGER.lock() {
+        Ok(logger) => logger,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    f(logger.get_or_insert_with(Logger::new));
+}
+
+fn is_module_prefix(prefix: &str, module: &str) -> bool {
+    return module == prefix
+        || (module.starts_with(prefix) && module[prefix.len()..].starts_with("::"));
+}
+
+pub fn set_level(level: Level) {
+    with_logger(|logger| logger.level = level);
+}
+
+/// Overrides the level for a module and everything below it, e.g. "violetta::systems"
+pub fn set_module_level(module: &str, level: Level) {
+    with_logger(|logger| {
+        logger
+            .module_levels
+            .retain(|&(ref prefix, _)| prefix != module);
+        logger.module_levels.push((module.to_string(), level));
+    });
+}
+
+pub fn add_sink(sink: Box<Sink>) {
+    with_logger(|logger| logger.sinks.push(sink));
+}
+
+/// Removes every sink, including the default stderr one
+pub fn clear_sinks() {
+    with_logger(|logger| {
+        for sink in &mut logger.sinks {
+            sink.flush();
+        }
+        logger.sinks.clear();
+    });
+}
+
+pub fn flush() {
+    with_logger(|logger| {
+        for sink in &mut logger.sinks {
+            sink.flush();
+        }
+    });
+}
+
+/// Called by the game runner at the start of every frame
+pub fn set_frame(frame: u64) {
+    with_logger(|logger| logger.frame = frame);
+}
+
+pub fn enabled(level: Level, module: &str) -> bool {
+    let mut enabled = false;
+    with_logger(|logger| enabled = logger.enabled(level, module));
+    return enabled;
+}
+
+pub fn log(level: Level, module: &str, args: fmt::Arguments) {
+    let mut stamp = None;
+    with_logger(|logger| {
+        if logger.enabled(level, module) {
+            stamp = Some((logger.epoch.elapsed(), logger.frame));
+        }
+    });
+    let (timestamp, frame) = match stamp {
+        Some(stamp) => stamp,
+        None => return,
+    };
+
+    // Formatting runs the arguments' `Display` impls, which may log themselves, so it
+    // happens without holding the logger
+    let record = Record {
+        level: level,
+        module: module.to_string(),
+        timestamp: timestamp,
+        frame: frame,
+        message: fmt::format(args),
+    };
+
+    with_logger(|logger| {
+        for sink in &mut logger.sinks {
+            sink.write(&record);
+        }
+    });
+}
+
+/// Logs a message at the level, e.g. `vlog!(Level::Info, "Loaded {}", name)`. The macros
+/// are prefixed so games can use them alongside the `log` crate's.
+#[macro_export]
+macro_rules! vlog {
+    ($level:expr, $($arg:tt)+) => {
+        $crate::util::log::log($level, module_path!(), format_args!($($arg)+))
+    };
+}
+
+/// Logs a message at `Level::Error`
+#[macro_export]
+macro_rules! vlog_error {
+    ($($arg:tt)+) => {
+        $crate::util::log::log(
+            $crate::util::log::Level::Error,
+            module_path!(),
+            format_args!($($arg)+),
+        )
+    };
+}
+
+/// Logs a message at `Level::Warn`
+#[macro_export]
+macro_rules! vlog_warn {
+    ($($arg:tt)+) => {
+        $crate::util::log::log(
+            $crate::util::log::Level::Warn,
+            module_path!(),
+            format_args!($($arg)+),
+        )
+    };
+}
+
+/// Logs a message at `Level::Info`
+#[macro_export]
+macro_rules! vlog_info {
+    ($($arg:tt)+) => {
+        $crate::util::log::log(
+            $crate::util::log::Level::Info,
+            module_path!(),
+            format_args!($($arg)+),
+        )
+    };
+}
+
+/// Logs a message at `Level::Debug`
+#[macro_export]
+macro_rules! vlog_debug {
+    ($($arg:tt)+) => {
+        $crate::util::log::log(
+            $crate::util::log::Level::Debug,
+            module_path!(),
+            format_args!($($arg)+),
+        )
+    };
+}
+
+/// Logs a message at `Level::Trace`
+#[macro_export]
+macro_rules! vlog_trace {
+    ($($arg:tt)+) => {
+        $crate::util::log::log(
+            $crate::util::log::Level::Trace,
+            module_path!(),
+            format_args!($($arg)+),
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Chatty;
+
+    impl fmt::Display for Chatty {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            vlog_info!("formatting");
+            return write!(f, "chatty");
+        }
+    }
+
+    struct LoggingSink;
+
+    impl Sink for LoggingSink {
+        fn write(&mut self, record: &Record) {
+            if record.message == "to the logging sink" {
+                vlog_warn!("written");
+            }
+        }
+    }
+
+    #[test]
+    fn logging_while_logging_does_not_deadlock() {
+        let sink = MemorySink::new(16);
+        let buffer = sink.buffer();
+        add_sink(Box::new(sink));
+        add_sink(Box::new(LoggingSink));
+
+        vlog_info!("{}", Chatty);
+        vlog_info!("to the logging sink");
+
+        let messages: Vec<String> = buffer
+            .records()
+            .into_iter()
+            .map(|record| record.message)
+            .collect();
+        assert!(messages.contains(&"formatting".to_string()));
+        assert!(messages.contains(&"chatty".to_string()));
+        assert!(messages.contains(&"to the logging sink".to_string()));
+        assert!(!messages.contains(&"written".to_string()));
+    }
+}
@@ -1,8 +1,4 @@
-extern crate gl;
-
-use gl::types::GLfloat;
-
-pub type Mat4 = [GLfloat; 16];
+pub type Mat4 = [f32; 16];
 
 pub fn identity() -> Mat4 {
     return [
@@ -24,7 +20,7 @@ pub fn to_identity(target: &mut Mat4) {
     target[15] = 1.0;
 }
 
-// Stores the result in left
+/// Stores the result in left
 pub fn multiply(left: &mut Mat4, right: &Mat4) {
     // TODO: Improve this
     let mut temp = [0.0f32; 16];
@@ -48,7 +44,7 @@ pub fn translate(what: &mut Mat4, dx: f32, dy: f32, dz: f32) {
     multiply(what, &translate);
 }
 
-pub fn scale(target: &mut Mat4, scale_x: GLfloat, scale_y: GLfloat, scale_z: GLfloat) {
+pub fn scale(target: &mut Mat4, scale_x: f32, scale_y: f32, scale_z: f32) {
     let scale: Mat4 = [
         scale_x, 0.0, 0.0, 0.0, //
         0.0, scale_y, 0.0, 0.0, //
@@ -120,3 +116,115 @@ pub fn rotate(target: &mut Mat4, x_rads: f32, y_rads: f32, z_rads: f32) {
     multiply(target, &r_y);
     multiply(target, &r_z);
 }
+
+/// The inverse of the matrix, or None if it has none, e.g. because it flattens an axis
+pub fn inverse(m: &Mat4) -> Option<Mat4> {
+    // Cofactors of the first two and last two rows, shared between the determinants
+    let s0 = m[0] * m[5] - m[4] * m[1];
+    let s1 = m[0] * m[6] - m[4] * m[2];
+    let s2 = m[0] * m[7] - m[4] * m[3];
+    let s3 = m[1] * m[6] - m[5] * m[2];
+    let s4 = m[1] * m[7] - m[5] * m[3];
+    let s5 = m[2] * m[7] - m[6] * m[3];
+
+    let c5 = m[10] * m[15] - m[14] * m[11];
+    let c4 = m[9] * m[15] - m[13] * m[11];
+    let c3 = m[9] * m[14] - m[13] * m[10];
+    let c2 = m[8] * m[15] - m[12] * m[11];
+    let c1 = m[8] * m[14] - m[12] * m[10];
+    let c0 = m[8] * m[13] - m[12] * m[9];
+
+    let determinant = s0 * c5 - s1 * c4 + s2 * c3 + s3 * c2 - s4 * c1 + s5 * c0;
+    // Small determinants are fine, a 2D view of a large world scales by tiny factors
+    if determinant == 0.0 || !determinant.is_finite() {
+        return None;
+    }
+
+    let scale = 1.0 / determinant;
+    return Some([
+        (m[5] * c5 - m[6] * c4 + m[7] * c3) * scale,
+        (-m[1] * c5 + m[2] * c4 - m[3] * c3) * scale,
+        (m[13] * s5 - m[14] * s4 + m[15] * s3) * scale,
+        (-m[9] * s5 + m[10] * s4 - m[11] * s3) * scale,
+        (-m[4] * c5 + m[6] * c2 - m[7] * c1) * scale,
+        (m[0] * c5 - m[2] * c2 + m[3] * c1) * scale,
+        (-m[12] * s5 + m[14] * s2 - m[15] * s1) * scale,
+        (m[8] * s5 - m[10] * s2 + m[11] * s1) * scale,
+        (m[4] * c4 - m[5] * c2 + m[7] * c0) * scale,
+        (-m[0] * c4 + m[1] * c2 - m[3] * c0) * scale,
+        (m[12] * s4 - m[13] * s2 + m[15] * s0) * scale,
+        (-m[8] * s4 + m[9] * s2 - m[11] * s0) * scale,
+        (-m[4] * c3 + m[5] * c1 - m[6] * c0) * scale,
+        (m[0] * c3 - m[1] * c1 + m[2] * c0) * scale,
+        (-m[12] * s3 + m[13] * s1 - m[14] * s0) * scale,
+        (m[8] * s3 - m[9] * s1 + m[10] * s0) * scale,
+    ]);
+}
+
+/// Multiplies the matrix with the column vector
+pub fn transform(m: &Mat4, v: [f32; 4]) -> [f32; 4] {
+    let mut result = [0.0f32; 4];
+    for row in 0..4 {
+        for column in 0..4 {
+            result[row] += m[row * 4 + column] * v[column];
+        }
+    }
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &Mat4, expected: &Mat4) {
+        for (index, (a, e)) in actual.iter().zip(expected.iter()).enumerate() {
+            assert!((a - e).abs() < 1e-5, "element {}: {} != {}", index, a, e);
+        }
+    }
+
+    #[test]
+    fn inverse_of_a_known_matrix() {
+        let m = [
+            2.0, 0.0, 0.0, 3.0, //
+            0.0, 4.0, 0.0, -1.0, //
+            0.0, 0.0, 1.0, 5.0, //
+            0.0, 0.0, 0.0, 1.0, //
+        ];
+        let expected = [
+            0.5, 0.0, 0.0, -1.5, //
+            0.0, 0.25, 0.0, 0.25, //
+            0.0, 0.0, 1.0, -5.0, //
+            0.0, 0.0, 0.0, 1.0, //
+        ];
+        assert_close(&inverse(&m).unwrap(), &expected);
+    }
+
+    #[test]
+    fn inverse_times_matrix_is_identity() {
+        let m = [
+            1.0, 2.0, 0.0, 1.0, //
+            0.0, 1.0, 3.0, 0.0, //
+            4.0, 0.0, 1.0, 2.0, //
+            0.0, 1.0, 0.0, 1.0, //
+        ];
+        let mut product = m;
+        multiply(&mut product, &inverse(&m).unwrap());
+        assert_close(&product, &identity());
+    }
+
+    #[test]
+    fn singular_matrix_has_no_inverse() {
+        let m = [
+            1.0, 2.0, 3.0, 4.0, //
+            2.0, 4.0, 6.0, 8.0, //
+            0.0, 1.0, 0.0, 1.0, //
+            5.0, 0.0, 1.0, 0.0, //
+        ];
+        assert!(inverse(&m).is_none());
+
+        // The view matrix flattens depth
+        let mut view = identity();
+        scale(&mut view, 0.5, 0.5, 0.0);
+        assert!(inverse(&view).is_none());
+    }
+}
@@ -1,2 +1,12 @@
+//! Logging, tracing, hashing, easing, matrix and text parsing helpers.
+
+#[macro_use]
+pub mod log;
+
+pub mod easing;
+pub mod hash;
+pub mod lines;
 pub mod matrix;
+#[cfg(feature = "rendering")]
 pub mod shader;
+pub mod trace;
@@ -1,11 +1,11 @@
 extern crate gl;
 
+use error::EngineError;
 use gl::types::{GLchar, GLenum, GLint, GLuint};
 use std::ffi::CString;
 use std::ptr;
-use std::str;
 
-pub fn compile_shader(src: &str, ty: GLenum) -> GLuint {
+pub fn compile_shader(src: &str, ty: GLenum) -> Result<GLuint, EngineError> {
     let shader;
     unsafe {
         shader = gl::CreateShader(ty);
@@ -22,26 +22,30 @@ pub fn compile_shader(src: &str, ty: GLenum) -> GLuint {
         if status != (gl::TRUE as GLint) {
             let mut len = 0;
             gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
-            let mut buf = Vec::with_capacity(len as usize);
-            buf.set_len((len as usize) - 1); // subtract 1 to skip the trailing null character
+            let mut buf = vec![0u8; len as usize];
             gl::GetShaderInfoLog(
                 shader,
                 len,
                 ptr::null_mut(),
                 buf.as_mut_ptr() as *mut GLchar,
             );
-            panic!(
-                "{}",
-                str::from_utf8(&buf)
-                    .ok()
-                    .expect("ShaderInfoLog not valid utf8")
-            );
+            gl::DeleteShader(shader);
+
+            let stage = match ty {
+                gl::VERTEX_SHADER => "vertex",
+                gl::FRAGMENT_SHADER => "fragment",
+                _ => "unknown",
+            };
+            return Err(EngineError::ShaderCompile {
+                stage: stage,
+                log: info_log(&buf),
+            });
         }
     }
-    shader
+    Ok(shader)
 }
 
-pub fn link_program(vs: GLuint, fs: GLuint) -> GLuint {
+pub fn link_program(vs: GLuint, fs: GLuint) -> Result<GLuint, EngineError> {
     unsafe {
         let program = gl::CreateProgram();
         gl::AttachShader(program, vs);
@@ -55,21 +59,25 @@ pub fn link_program(vs: GLuint, fs: GLuint) -> GLuint {
         if status != (gl::TRUE as GLint) {
             let mut len: GLint = 0;
             gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len);
-            let mut buf = Vec::with_capacity(len as usize);
-            buf.set_len((len as usize) - 1); // subtract 1 to skip the trailing null character
+            let mut buf = vec![0u8; len as usize];
             gl::GetProgramInfoLog(
                 program,
                 len,
                 ptr::null_mut(),
                 buf.as_mut_ptr() as *mut GLchar,
             );
-            panic!(
-                "{}",
-                str::from_utf8(&buf)
-                    .ok()
-                    .expect("ProgramInfoLog not valid utf8")
-            );
+            gl::DeleteProgram(program);
+
+            return Err(EngineError::ProgramLink {
+                log: info_log(&buf),
+            });
         }
-        program
+        Ok(program)
     }
 }
+
+// Info logs are null terminated and not guaranteed to be valid utf8
+fn info_log(buf: &[u8]) -> String {
+    let end = buf.iter().position(|&byte| byte == 0).unwrap_or(buf.len());
+    return String::from_utf8_lossy(&buf[..end]).trim_end().to_string();
+}
@@ -0,0 +1,167 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const DEFAULT_FRAME_CAPACITY: usize = 120;
+
+struct Event {
+    name: &'static str,
+    start: Duration,
+    duration: Duration,
+}
+
+struct Frame {
+    number: u64,
+    events: Vec<Event>,
+}
+
+// Keeps the scopes of the last few frames so they can be dumped to a trace file
+struct Tracer {
+    epoch: Instant,
+    enabled: bool,
+    frame_capacity: usize,
+    frames: VecDeque<Frame>,
+}
+
+thread_local! {
+    static TRACER: RefCell<Tracer> = RefCell::new(Tracer {
+        epoch: Instant::now(),
+        enabled: true,
+        frame_capacity: DEFAULT_FRAME_CAPACITY,
+        frames: VecDeque::new(),
+    });
+}
+
+/// Records the time between its creation and when it is dropped or ended. Scopes nest,
+/// so a scope opened while another is alive shows up inside it in the trace viewer.
+pub struct Scope {
+    name: &'static str,
+    start: Instant,
+}
+
+impl Scope {
+    /// Closes the scope and returns how long it was open
+    pub fn end(self) -> Duration {
+        return self.start.elapsed();
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let end = Instant::now();
+        TRACER.with(|tracer| {
+            let mut tracer = tracer.borrow_mut();
+            if !tracer.enabled {
+                return;
+            }
+
+            let start = self.start.duration_since(tracer.epoch);
+            if tracer.frames.is_empty() {
+                tracer.frames.push_back(Frame {
+                    number: 0,
+                    events: Vec::new(),
+                });
+            }
+
+            if let Some(frame) = tracer.frames.back_mut() {
+                frame.events.push(Event {
+                    name: self.name,
+                    start: start,
+                    duration: end.duration_since(self.start),
+                });
+            }
+        });
+    }
+}
+
+pub fn scope(name: &'static str) -> Scope {
+    return Scope {
+        name: name,
+        start: Instant::now(),
+    };
+}
+
+/// Starts collecting scopes for a new frame, dropping the oldest one if the buffer is full
+pub fn begin_frame(number: u64) {
+    TRACER.with(|tracer| {
+        let mut tracer = tracer.borrow_mut();
+        if !tracer.enabled {
+            return;
+        }
+
+        while tracer.frames.len() >= tracer.frame_capacity {
+            tracer.frames.pop_front();
+        }
+
+        tracer.frames.push_back(Frame {
+            number: number,
+            events: Vec::new(),
+        });
+    });
+}
+
+pub fn set_enabled(enabled: bool) {
+    TRACER.with(|tracer| {
+        let mut tracer = tracer.borrow_mut();
+        tracer.enabled = enabled;
+        if !enabled {
+            tracer.frames.clear();
+        }
+    });
+}
+
+/// How many frames of scopes are kept
+pub fn set_frame_capacity(frame_capacity: usize) {
+    TRACER.with(|tracer| {
+        let mut tracer = tracer.borrow_mut();
+        tracer.frame_capacity = frame_capacity.max(1);
+        while tracer.frames.len() > tracer.frame_capacity {
+            tracer.frames.pop_front();
+        }
+    });
+}
+
+/// Writes the buffered frames as Chrome trace event JSON, viewable in chrome://tracing or
+/// any compatible viewer
+pub fn write_chrome_trace<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    TRACER.with(|tracer| -> io::Result<()> {
+        let tracer = tracer.borrow();
+
+        writer.write_all(b"{\"traceEvents\":[")?;
+        let mut first = true;
+        for frame in &tracer.frames {
+            for event in &frame.events {
+                if !first {
+                    writer.write_all(b",")?;
+                }
+                first = false;
+
+                write!(
+                    writer,
+                    "\n{{\"name\":\"{}\",\"cat\":\"engine\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":1,\"tid\":1,\"args\":{{\"frame\":{}}}}}",
+                    escape(event.name),
+                    micros(event.start),
+                    micros(event.duration),
+                    frame.number
+                )?;
+            }
+        }
+        writer.write_all(b"\n]}\n")?;
+        return Ok(());
+    })?;
+
+    return writer.flush();
+}
+
+fn micros(duration: Duration) -> f64 {
+    return duration.as_secs() as f64 * 1_000_000.0 + duration.subsec_nanos() as f64 / 1_000.0;
+}
+
+fn escape(name: &str) -> String {
+    return name.replace('\\', "\\\\").replace('"', "\\\"");
+}